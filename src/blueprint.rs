@@ -54,7 +54,7 @@ impl TaskQueue {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
 pub enum TaskStatus {
     Done,
     InProgress,
@@ -62,6 +62,18 @@ pub enum TaskStatus {
     Icebox,
 }
 
+impl TaskStatus {
+    /// Name of the Layer 4 section holding tasks with this status (`### DONE`, ...)
+    pub fn section_name(&self) -> &'static str {
+        match self {
+            TaskStatus::Done => "DONE",
+            TaskStatus::InProgress => "IN PROGRESS",
+            TaskStatus::NextUp => "NEXT UP",
+            TaskStatus::Icebox => "ICEBOX",
+        }
+    }
+}
+
 impl std::fmt::Display for TaskStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -79,6 +91,8 @@ pub struct ValidationResult {
     pub layer_name: String,
     pub status: ValidationStatus,
     pub message: String,
+    /// What to write to make a failed check pass
+    pub fix: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -97,3 +111,67 @@ impl std::fmt::Display for ValidationStatus {
         }
     }
 }
+
+/// A validation finding tied to a place in the blueprint, for editors and tools
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    /// Stable rule id, e.g. `empty-layer` or `task-missing-context`
+    pub rule: &'static str,
+    /// Blueprint layer (1-4) the check belongs to
+    pub layer: u8,
+    pub severity: Severity,
+    pub range: Range,
+    pub message: String,
+    /// What to write to make the diagnostic go away
+    pub fix: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Span in the blueprint text; lines and columns start at 1, columns count characters,
+/// and `end` is exclusive
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Range {
+    /// The whole of line `line` (1-based) in `content`
+    pub fn line(content: &str, line: usize) -> Self {
+        let len = content
+            .lines()
+            .nth(line.saturating_sub(1))
+            .map(|l| l.chars().count())
+            .unwrap_or(0);
+        Range {
+            start: Position { line, column: 1 },
+            end: Position {
+                line,
+                column: len + 1,
+            },
+        }
+    }
+}
+
+/// A heading in the blueprint and the lines its section covers (1-based, inclusive)
+#[derive(Debug, Clone, Serialize)]
+pub struct Heading {
+    /// Number of `#`
+    pub level: usize,
+    pub title: String,
+    pub line: usize,
+    /// Last line before the next heading of the same or higher level
+    pub end_line: usize,
+}
@@ -0,0 +1,105 @@
+use base64::Engine;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Some terminals silently drop OSC 52 payloads beyond roughly this size
+const OSC52_SOFT_LIMIT: usize = 100_000;
+
+/// How the text reached the clipboard
+pub enum CopyMethod {
+    /// A native clipboard tool (pbcopy, wl-copy, xclip, ...)
+    Tool(&'static str),
+    /// OSC 52 escape sequence written to the terminal
+    Osc52,
+}
+
+/// Copy text to the system clipboard.
+/// Over SSH the local clipboard is on the other machine, so OSC 52 is used directly;
+/// otherwise native tools are tried first and OSC 52 is the fallback.
+pub fn copy(text: &str) -> io::Result<CopyMethod> {
+    let over_ssh = std::env::var_os("SSH_TTY").is_some() || std::env::var_os("SSH_CONNECTION").is_some();
+
+    if !over_ssh {
+        for (program, args) in clipboard_tools() {
+            if pipe_to(program, args, text).is_ok() {
+                return Ok(CopyMethod::Tool(program));
+            }
+        }
+    }
+
+    copy_osc52(text)?;
+    Ok(CopyMethod::Osc52)
+}
+
+/// Native clipboard commands for this platform, in order of preference
+fn clipboard_tools() -> Vec<(&'static str, &'static [&'static str])> {
+    if cfg!(target_os = "macos") {
+        vec![("pbcopy", &[])]
+    } else if cfg!(target_os = "windows") {
+        vec![("clip", &[])]
+    } else {
+        let mut tools: Vec<(&'static str, &'static [&'static str])> = Vec::new();
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            tools.push(("wl-copy", &[]));
+        }
+        if std::env::var_os("DISPLAY").is_some() {
+            tools.push(("xclip", &["-selection", "clipboard"]));
+            tools.push(("xsel", &["--clipboard", "--input"]));
+        }
+        // WSL
+        tools.push(("clip.exe", &[]));
+        tools
+    }
+}
+
+/// Run a program and feed `text` on its stdin
+fn pipe_to(program: &str, args: &[&str], text: &str) -> io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("{} exited with {}", program, status)));
+    }
+    Ok(())
+}
+
+/// Ask the terminal emulator to set the clipboard via an OSC 52 escape sequence
+fn copy_osc52(text: &str) -> io::Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    if encoded.len() > OSC52_SOFT_LIMIT {
+        eprintln!(
+            "Warning: {} KB is large for OSC 52; some terminals will truncate or ignore it.",
+            encoded.len() / 1024
+        );
+    }
+
+    let mut sequence = format!("\x1b]52;c;{}\x07", encoded);
+
+    // tmux only forwards escape sequences wrapped in a DCS passthrough
+    if std::env::var_os("TMUX").is_some() {
+        sequence = format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"));
+    }
+
+    // Prefer the controlling terminal so the sequence survives stdout redirection
+    let tty = std::fs::OpenOptions::new().write(true).open("/dev/tty");
+    match tty {
+        Ok(mut tty) => {
+            tty.write_all(sequence.as_bytes())?;
+            tty.flush()
+        }
+        Err(_) => {
+            let mut stderr = io::stderr();
+            stderr.write_all(sequence.as_bytes())?;
+            stderr.flush()
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use crate::blueprint::Severity;
+use crate::template::Schema;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const FILE_NAME: &str = "nira.toml";
+/// Blueprint file names looked for when no file is given, in order of preference
+pub const DEFAULT_FILENAMES: [&str; 2] = ["niraprint.md", "blueprint.md"];
+pub const DEFAULT_PORT: u16 = 3141;
+
+/// Project settings from `nira.toml`, or a `[tool.nira]` / `[package.metadata.nira]`
+/// table in `Cargo.toml`. Relative paths are relative to the file they're set in.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Blueprint used when a command is given no file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blueprint: Option<PathBuf>,
+    /// Names a blueprint may have, in order of preference (`DEFAULT_FILENAMES` when empty)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filenames: Vec<String>,
+    /// Sections `nira validate` expects the blueprint to have
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Schema>,
+    /// Port for `nira serve`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Snapshots kept per blueprint in `.nira/history/` (0 disables history)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_history: Option<usize>,
+    /// Validation rule id to level, e.g. `task-missing-approach = "off"`
+    pub rules: BTreeMap<String, Level>,
+    pub prompts: Prompts,
+    /// Paths left out of workspace discovery and of the changed files in recovery prompts
+    pub ignore: Vec<String>,
+}
+
+/// Files whose text replaces the rules at the end of each kind of prompt
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Prompts {
+    /// `nira prompt` and exported agent instructions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub full: Option<PathBuf>,
+    /// `nira prompt --task N`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task: Option<PathBuf>,
+    /// `nira prompt --recover`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recover: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Off,
+    Warning,
+    Error,
+}
+
+impl From<Severity> for Level {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Error => Level::Error,
+            Severity::Warning => Level::Warning,
+        }
+    }
+}
+
+/// The settings in effect and where they came from
+#[derive(Debug, Clone, Default)]
+pub struct Loaded {
+    pub config: Config,
+    /// File the settings were read from, `None` when running on defaults
+    pub source: Option<PathBuf>,
+    /// Directory relative paths and ignore globs are taken from: the config file's, else
+    /// the repository root, else the directory nira runs in
+    root: PathBuf,
+    ignore: Vec<Regex>,
+}
+
+/// The blueprint picked when no file is given
+#[derive(Debug, Clone)]
+pub struct Found {
+    pub path: PathBuf,
+    /// Less preferred candidates in the same directory, which were not used
+    pub passed_over: Vec<PathBuf>,
+}
+
+/// Walk up from `dir` to the first `nira.toml`, or `Cargo.toml` with a nira table
+pub fn load(dir: &Path) -> io::Result<Loaded> {
+    for ancestor in search_dirs(dir) {
+        let path = ancestor.join(FILE_NAME);
+        if path.is_file() {
+            let config = parse(&std::fs::read_to_string(&path)?).map_err(|e| invalid(&path, e))?;
+            return Loaded::new(config, path);
+        }
+
+        let path = ancestor.join("Cargo.toml");
+        if path.is_file() {
+            if let Some(config) = from_cargo_toml(&std::fs::read_to_string(&path)?).map_err(|e| invalid(&path, e))? {
+                return Loaded::new(config, path);
+            }
+        }
+    }
+    Ok(Loaded {
+        root: repo_root(dir).unwrap_or(dir).to_path_buf(),
+        ..Loaded::default()
+    })
+}
+
+/// `dir` and its parents up to the repository root (the first one with a `.git`), or
+/// up to the filesystem root outside a repository
+fn search_dirs(dir: &Path) -> impl Iterator<Item = &Path> {
+    let mut past_root = false;
+    dir.ancestors().take_while(move |ancestor| {
+        let inside = !past_root;
+        past_root = past_root || ancestor.join(".git").exists();
+        inside
+    })
+}
+
+/// The repository root at or above `dir`: the first directory with a `.git`
+pub fn repo_root(dir: &Path) -> Option<&Path> {
+    dir.ancestors().find(|ancestor| ancestor.join(".git").exists())
+}
+
+/// `path` relative to `dir`, going up with `..` when `dir` is inside the path's directory
+pub fn relative_to(dir: &Path, path: &Path) -> PathBuf {
+    for (depth, ancestor) in dir.ancestors().enumerate() {
+        if ancestor.parent().is_none() {
+            break;
+        }
+        if let Ok(rest) = path.strip_prefix(ancestor) {
+            let mut relative: PathBuf = std::iter::repeat_n("..", depth).collect();
+            relative.push(rest);
+            return relative;
+        }
+    }
+    path.to_path_buf()
+}
+
+fn invalid(path: &Path, e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+}
+
+pub fn parse(content: &str) -> Result<Config, String> {
+    check(toml::from_str(content).map_err(|e| e.message().to_string())?)
+}
+
+fn check(config: Config) -> Result<Config, String> {
+    if let Some(name) = config.filenames.iter().find(|name| name.is_empty() || name.contains(['/', '\\'])) {
+        return Err(format!("Blueprint filenames must be plain file names, got '{}'", name));
+    }
+    if let Some(rule) = config.rules.keys().find(|r| !crate::validator::RULES.iter().any(|(id, _)| id == r)) {
+        let known: Vec<&str> = crate::validator::RULES.iter().map(|(id, _)| *id).collect();
+        return Err(format!("Unknown rule '{}' (rules: {})", rule, known.join(", ")));
+    }
+    Ok(config)
+}
+
+/// The `[tool.nira]` or `[package.metadata.nira]` table, if there is one
+fn from_cargo_toml(content: &str) -> Result<Option<Config>, String> {
+    let manifest: toml::Table = toml::from_str(content).map_err(|e| e.message().to_string())?;
+    let table = manifest
+        .get("tool")
+        .and_then(|t| t.get("nira"))
+        .or_else(|| manifest.get("package")?.get("metadata")?.get("nira"));
+    match table {
+        Some(table) => check(table.clone().try_into().map_err(|e: toml::de::Error| e.message().to_string())?).map(Some),
+        None => Ok(None),
+    }
+}
+
+impl Loaded {
+    fn new(config: Config, source: PathBuf) -> io::Result<Loaded> {
+        let root = source.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        let prompts = [&config.prompts.full, &config.prompts.task, &config.prompts.recover];
+        if let Some(missing) = prompts.into_iter().flatten().map(|p| root.join(p)).find(|p| !p.is_file()) {
+            return Err(invalid(&source, format!("Prompt file {} not found", missing.display())));
+        }
+
+        let ignore = config
+            .ignore
+            .iter()
+            .map(|glob| glob_regex(glob))
+            .collect::<Result<_, _>>()
+            .map_err(|e| invalid(&source, e))?;
+
+        Ok(Loaded {
+            config,
+            source: Some(source),
+            root,
+            ignore,
+        })
+    }
+
+    /// Blueprint file names to look for, most preferred first
+    pub fn filenames(&self) -> Vec<&str> {
+        if self.config.filenames.is_empty() {
+            DEFAULT_FILENAMES.to_vec()
+        } else {
+            self.config.filenames.iter().map(String::as_str).collect()
+        }
+    }
+
+    /// The nearest blueprint walking up from the current directory like git does: a file
+    /// with one of the candidate names, or the blueprint of the directory with the config
+    /// file, which ends the search as the repository root does. Falls back to the first
+    /// candidate name in the config file's directory, or else in the current directory.
+    pub fn find_blueprint(&self) -> Found {
+        let fallback = || Found {
+            path: PathBuf::from(self.filenames()[0]),
+            passed_over: vec![],
+        };
+        let Ok(cwd) = std::env::current_dir() else {
+            return fallback();
+        };
+
+        for dir in search_dirs(&cwd) {
+            if self.source.is_some() && dir == self.root {
+                if let Some(configured) = &self.config.blueprint {
+                    return Found {
+                        path: relative_to(&cwd, &dir.join(configured)),
+                        passed_over: vec![],
+                    };
+                }
+            }
+
+            let mut present = self
+                .filenames()
+                .into_iter()
+                .map(|name| dir.join(name))
+                .filter(|path| path.is_file())
+                .map(|path| relative_to(&cwd, &path));
+            if let Some(path) = present.next() {
+                return Found {
+                    path,
+                    passed_over: present.collect(),
+                };
+            }
+            if self.source.is_some() && dir == self.root {
+                return Found {
+                    path: relative_to(&cwd, &dir.join(self.filenames()[0])),
+                    passed_over: vec![],
+                };
+            }
+        }
+        fallback()
+    }
+
+    /// Where `nira init` creates a blueprint: the configured one, or the first candidate
+    /// name next to the config file or in the current directory. Never a blueprint found
+    /// further up.
+    pub fn new_blueprint(&self) -> PathBuf {
+        let name = Path::new(self.filenames()[0]);
+        if self.source.is_none() {
+            return name.to_path_buf();
+        }
+        let path = self.root.join(self.config.blueprint.as_deref().unwrap_or(name));
+        match std::env::current_dir() {
+            Ok(cwd) => relative_to(&cwd, &path),
+            Err(_) => path,
+        }
+    }
+
+    /// Where project files like `.nira/templates/` live
+    pub fn project_root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn port(&self, port: Option<u16>) -> u16 {
+        port.or(self.config.port).unwrap_or(DEFAULT_PORT)
+    }
+
+    pub fn keep_history(&self, keep: Option<usize>) -> usize {
+        keep.or(self.config.keep_history).unwrap_or(crate::history::DEFAULT_KEEP)
+    }
+
+    pub fn schema(&self) -> Schema {
+        self.config.schema.unwrap_or(Schema::Layers)
+    }
+
+    /// Severity of a validation rule, `None` when it's turned off
+    pub fn severity(&self, rule: &str, default: Severity) -> Option<Severity> {
+        match self.config.rules.get(rule).copied().unwrap_or(default.into()) {
+            Level::Off => None,
+            Level::Warning => Some(Severity::Warning),
+            Level::Error => Some(Severity::Error),
+        }
+    }
+
+    /// Text of a configured prompt file
+    pub fn prompt(&self, path: &Option<PathBuf>) -> Option<String> {
+        let mut text = std::fs::read_to_string(self.resolve(path.as_ref()?)).ok()?;
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        Some(text)
+    }
+
+    /// Whether `path` (or a directory it's in) matches one of the ignore globs, which
+    /// are matched against paths relative to the config file
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        if self.ignore.is_empty() {
+            return false;
+        }
+        let absolute = std::env::current_dir().map(|cwd| cwd.join(path)).unwrap_or_else(|_| path.to_path_buf());
+        let Ok(relative) = absolute.strip_prefix(&self.root) else {
+            return false;
+        };
+
+        let mut prefix = String::new();
+        relative.components().any(|component| {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(&component.as_os_str().to_string_lossy());
+            self.ignore.iter().any(|glob| glob.is_match(&prefix))
+        })
+    }
+
+    /// A configured path, which is relative to the config file
+    fn resolve(&self, path: &Path) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// Every setting with its effective value, as TOML that could go in a `nira.toml`
+    pub fn effective(&self) -> String {
+        let mut config = self.config.clone();
+        config.filenames = self.filenames().into_iter().map(String::from).collect();
+        config.schema = Some(self.schema());
+        config.port = Some(self.port(None));
+        config.keep_history = Some(self.keep_history(None));
+        for (rule, default) in crate::validator::RULES {
+            config.rules.entry(rule.to_string()).or_insert(default.into());
+        }
+        toml::to_string(&config).unwrap_or_default()
+    }
+}
+
+/// Anchored regex for a glob: `*` and `?` stay within a path segment, `**` crosses
+/// them, and a glob without a `/` matches a name at any depth
+fn glob_regex(glob: &str) -> Result<Regex, String> {
+    let glob = glob.trim_end_matches('/');
+    let mut pattern = String::from("^");
+    if !glob.contains('/') {
+        pattern.push_str("(.*/)?");
+    }
+
+    let mut chars = glob.trim_start_matches('/').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    pattern.push_str("(.*/)?");
+                } else {
+                    pattern.push_str(".*");
+                }
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).map_err(|e| format!("Bad ignore glob '{}': {}", glob, e))
+}
@@ -0,0 +1,269 @@
+use serde::Serialize;
+
+/// One region of a three-way comparison between a common base, our copy and theirs
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Hunk {
+    /// Lines nobody changed
+    Unchanged { lines: Vec<String> },
+    /// Only we changed these base lines
+    Ours { base: Vec<String>, lines: Vec<String> },
+    /// Only they changed these base lines
+    Theirs { base: Vec<String>, lines: Vec<String> },
+    /// Both sides made the same change
+    Both { base: Vec<String>, lines: Vec<String> },
+    /// Both sides changed the same base lines differently
+    Conflict {
+        base: Vec<String>,
+        ours: Vec<String>,
+        theirs: Vec<String>,
+    },
+}
+
+/// Line-based three-way merge result
+#[derive(Debug, Clone, Serialize)]
+pub struct Merge {
+    pub hunks: Vec<Hunk>,
+    /// True when no hunk is a conflict
+    pub clean: bool,
+    /// Merged text; conflicts are written with git-style markers
+    pub merged: String,
+}
+
+/// Three-way merge of `ours` and `theirs`, both derived from `base` (diff3 by lines)
+pub fn merge3(base: &str, ours: &str, theirs: &str) -> Merge {
+    let base: Vec<&str> = base.split_inclusive('\n').collect();
+    let ours: Vec<&str> = ours.split_inclusive('\n').collect();
+    let theirs: Vec<&str> = theirs.split_inclusive('\n').collect();
+
+    let match_ours = lcs_matches(&base, &ours);
+    let match_theirs = lcs_matches(&base, &theirs);
+
+    let mut hunks = Vec::new();
+    let (mut i, mut a, mut b) = (0, 0, 0);
+
+    loop {
+        // Stable run: base lines matched in both copies at the current positions
+        let mut k = 0;
+        while i + k < base.len()
+            && match_ours[i + k] == Some(a + k)
+            && match_theirs[i + k] == Some(b + k)
+        {
+            k += 1;
+        }
+        if k > 0 {
+            push_unchanged(&mut hunks, &base[i..i + k]);
+            i += k;
+            a += k;
+            b += k;
+            continue;
+        }
+
+        // Unstable region up to the next base line both copies still contain
+        let next = (i..base.len()).find(|&j| match_ours[j].is_some() && match_theirs[j].is_some());
+        let (j, a_end, b_end) = match next {
+            Some(j) => (j, match_ours[j].unwrap_or(a), match_theirs[j].unwrap_or(b)),
+            None => (base.len(), ours.len(), theirs.len()),
+        };
+
+        if j == i && a_end == a && b_end == b {
+            break;
+        }
+
+        hunks.push(classify(&base[i..j], &ours[a..a_end], &theirs[b..b_end]));
+        i = j;
+        a = a_end;
+        b = b_end;
+    }
+
+    let clean = !hunks.iter().any(|h| matches!(h, Hunk::Conflict { .. }));
+    let merged = render(&hunks);
+
+    Merge { hunks, clean, merged }
+}
+
+fn push_unchanged(hunks: &mut Vec<Hunk>, lines: &[&str]) {
+    if let Some(Hunk::Unchanged { lines: existing }) = hunks.last_mut() {
+        existing.extend(lines.iter().map(|l| l.to_string()));
+    } else {
+        hunks.push(Hunk::Unchanged {
+            lines: to_owned(lines),
+        });
+    }
+}
+
+fn classify(base: &[&str], ours: &[&str], theirs: &[&str]) -> Hunk {
+    if ours == base {
+        Hunk::Theirs {
+            base: to_owned(base),
+            lines: to_owned(theirs),
+        }
+    } else if theirs == base {
+        Hunk::Ours {
+            base: to_owned(base),
+            lines: to_owned(ours),
+        }
+    } else if ours == theirs {
+        Hunk::Both {
+            base: to_owned(base),
+            lines: to_owned(ours),
+        }
+    } else {
+        Hunk::Conflict {
+            base: to_owned(base),
+            ours: to_owned(ours),
+            theirs: to_owned(theirs),
+        }
+    }
+}
+
+fn render(hunks: &[Hunk]) -> String {
+    let mut out = String::new();
+    for hunk in hunks {
+        match hunk {
+            Hunk::Unchanged { lines }
+            | Hunk::Ours { lines, .. }
+            | Hunk::Theirs { lines, .. }
+            | Hunk::Both { lines, .. } => push_lines(&mut out, lines),
+            Hunk::Conflict { ours, theirs, .. } => {
+                push_lines(&mut out, &["<<<<<<< yours\n".to_string()]);
+                push_lines(&mut out, ours);
+                push_lines(&mut out, &["=======\n".to_string()]);
+                push_lines(&mut out, theirs);
+                push_lines(&mut out, &[">>>>>>> current\n".to_string()]);
+            }
+        }
+    }
+    out
+}
+
+/// Append lines, making sure a line without a trailing newline doesn't run into the next one
+fn push_lines(out: &mut String, lines: &[String]) {
+    for line in lines {
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str(line);
+    }
+}
+
+fn to_owned(lines: &[&str]) -> Vec<String> {
+    lines.iter().map(|l| l.to_string()).collect()
+}
+
+/// For each line of `a`, the index of the line of `b` it is paired with in a longest common subsequence
+fn lcs_matches(a: &[&str], b: &[&str]) -> Vec<Option<usize>> {
+    // Trim the common prefix and suffix first; blueprint edits are usually small
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+
+    let mut matches = vec![None; a.len()];
+    for (i, m) in matches.iter_mut().enumerate().take(prefix) {
+        *m = Some(i);
+    }
+    for k in 0..suffix {
+        matches[a.len() - 1 - k] = Some(b.len() - 1 - k);
+    }
+
+    // Dynamic programming table of LCS lengths for the middle part
+    let (n, m) = (a_mid.len(), b_mid.len());
+    let mut table = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[i][j] = if a_mid[i] == b_mid[j] {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if a_mid[i] == b_mid[j] {
+            matches[prefix + i] = Some(prefix + j);
+            i += 1;
+            j += 1;
+        } else if table[i + 1][j] >= table[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    matches
+}
+
+/// One line of a two-way diff
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "text", rename_all = "snake_case")]
+pub enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Line diff turning `old` into `new`
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.split_inclusive('\n').collect();
+    let new: Vec<&str> = new.split_inclusive('\n').collect();
+    let matches = lcs_matches(&old, &new);
+
+    let mut out = Vec::new();
+    let mut j = 0;
+    for (i, line) in old.iter().enumerate() {
+        match matches[i] {
+            Some(m) => {
+                out.extend(new[j..m].iter().map(|l| DiffLine::Added(l.to_string())));
+                out.push(DiffLine::Same(line.to_string()));
+                j = m + 1;
+            }
+            None => out.push(DiffLine::Removed(line.to_string())),
+        }
+    }
+    out.extend(new[j..].iter().map(|l| DiffLine::Added(l.to_string())));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "one\ntwo\nthree\nfour\n";
+
+    #[test]
+    fn merge3_combines_changes_to_different_lines() {
+        let merge = merge3(BASE, "ONE\ntwo\nthree\nfour\n", "one\ntwo\nthree\nFOUR\nfive\n");
+        assert!(merge.clean);
+        assert_eq!(merge.merged, "ONE\ntwo\nthree\nFOUR\nfive\n");
+    }
+
+    #[test]
+    fn merge3_takes_the_same_change_once() {
+        let merge = merge3(BASE, "one\n2\nthree\nfour\n", "one\n2\nthree\nfour\n");
+        assert!(merge.clean);
+        assert_eq!(merge.merged, "one\n2\nthree\nfour\n");
+    }
+
+    #[test]
+    fn merge3_marks_conflicting_changes() {
+        let merge = merge3(BASE, "one\nours\nthree\nfour\n", "one\ntheirs\nthree\nfour\n");
+        assert!(!merge.clean);
+        assert_eq!(
+            merge.merged,
+            "one\n<<<<<<< yours\nours\n=======\ntheirs\n>>>>>>> current\nthree\nfour\n"
+        );
+        assert!(merge.hunks.iter().any(|hunk| matches!(
+            hunk,
+            Hunk::Conflict { base, .. } if base == &["two\n".to_string()]
+        )));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::ot::{OtError, TextOperation};
+use std::collections::VecDeque;
+
+/// How many committed revisions to keep for transforming late edits
+const HISTORY_LIMIT: usize = 1000;
+
+/// One committed edit and who made it
+#[derive(Debug, Clone)]
+pub struct Revision {
+    pub op: TextOperation,
+    /// Undoes `op`, for reconstructing older revisions
+    pub inverse: TextOperation,
+    /// Browser tab id, or "disk" / "rest" for edits that came from outside the socket
+    pub client_id: String,
+    /// Client-side sequence number (from 1), used to recognise resent edits; 0 for server-side edits
+    pub seq: u64,
+}
+
+#[derive(Debug)]
+pub enum EditError {
+    /// The edit is based on a revision older than the retained history
+    TooOld,
+    /// The edit claims a revision the server has not reached
+    FutureRevision,
+    /// The operation does not fit the document
+    Invalid(OtError),
+}
+
+impl std::fmt::Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EditError::TooOld => write!(f, "edit is based on a revision that is no longer in history"),
+            EditError::FutureRevision => write!(f, "edit is based on an unknown future revision"),
+            EditError::Invalid(e) => write!(f, "invalid operation: {}", e),
+        }
+    }
+}
+
+/// Result of committing an edit
+pub enum Applied {
+    /// The edit was transformed and committed at `revision`
+    Committed { revision: u64, op: TextOperation },
+    /// The same (client, seq) edit was committed earlier at `revision`
+    Duplicate { revision: u64 },
+}
+
+/// The authoritative copy of a blueprint being edited.
+/// Every change is an operation with a revision number; edits based on an older
+/// revision are transformed against everything committed since, so concurrent
+/// edits from tabs and from disk merge instead of overwriting each other.
+pub struct Document {
+    pub content: String,
+    pub revision: u64,
+    /// `history[i]` turned revision `history_start + i` into the next one
+    history: VecDeque<Revision>,
+    history_start: u64,
+    /// Content as last written to or read from disk, and the revision it matches
+    pub disk_content: String,
+    pub disk_revision: u64,
+}
+
+impl Document {
+    pub fn new(content: String) -> Self {
+        Document {
+            disk_content: content.clone(),
+            content,
+            revision: 0,
+            history: VecDeque::new(),
+            history_start: 0,
+            disk_revision: 0,
+        }
+    }
+
+    /// Committed revisions after `since`, or None if they are no longer retained
+    pub fn revisions_since(&self, since: u64) -> Option<Vec<(u64, Revision)>> {
+        if since < self.history_start || since > self.revision {
+            return None;
+        }
+        let skip = (since - self.history_start) as usize;
+        Some(
+            self.history
+                .iter()
+                .skip(skip)
+                .enumerate()
+                .map(|(i, rev)| (since + i as u64 + 1, rev.clone()))
+                .collect(),
+        )
+    }
+
+    /// Content as it was at `revision`, or None if that revision is no longer retained
+    pub fn content_at(&self, revision: u64) -> Option<String> {
+        let newer = self.revisions_since(revision)?;
+        let mut content = self.content.clone();
+        for (_, committed) in newer.iter().rev() {
+            content = committed.inverse.apply(&content).ok()?;
+        }
+        Some(content)
+    }
+
+    /// Transform an edit made against `base` up to the current revision and commit it
+    pub fn apply_edit(
+        &mut self,
+        base: u64,
+        op: TextOperation,
+        client_id: &str,
+        seq: u64,
+    ) -> Result<Applied, EditError> {
+        if base > self.revision {
+            return Err(EditError::FutureRevision);
+        }
+
+        let concurrent = self.revisions_since(base).ok_or(EditError::TooOld)?;
+
+        let mut op = op;
+        for (revision, committed) in &concurrent {
+            if seq > 0 && committed.client_id == client_id && committed.seq == seq {
+                return Ok(Applied::Duplicate { revision: *revision });
+            }
+            op = TextOperation::transform(&op, &committed.op)
+                .map_err(EditError::Invalid)?
+                .0;
+        }
+
+        // Applying checks the op's length against the document, which `invert` relies on
+        let content = op.apply(&self.content).map_err(EditError::Invalid)?;
+        let inverse = op.invert(&self.content);
+        self.content = content;
+        self.revision += 1;
+        self.history.push_back(Revision {
+            op: op.clone(),
+            inverse,
+            client_id: client_id.to_string(),
+            seq,
+        });
+        self.trim_history();
+
+        Ok(Applied::Committed {
+            revision: self.revision,
+            op,
+        })
+    }
+
+    /// Replace the whole text as one edit against the current revision
+    pub fn replace(&mut self, new_content: &str, client_id: &str) -> Option<(u64, TextOperation)> {
+        let op = TextOperation::from_diff(&self.content, new_content);
+        if op.is_noop() {
+            return None;
+        }
+        match self.apply_edit(self.revision, op, client_id, 0) {
+            Ok(Applied::Committed { revision, op }) => Some((revision, op)),
+            _ => None,
+        }
+    }
+
+    /// Merge a change found on disk. The diff is taken against what was last synced
+    /// with disk, so edits committed since then are kept. The caller must write the
+    /// merged content back (if it differs from `on_disk`) and call `mark_saved`, so
+    /// the next disk diff again starts from a known revision.
+    pub fn merge_disk(&mut self, on_disk: &str) -> Result<Option<(u64, TextOperation)>, EditError> {
+        if on_disk == self.disk_content {
+            return Ok(None);
+        }
+
+        let op = TextOperation::from_diff(&self.disk_content, on_disk);
+        match self.apply_edit(self.disk_revision, op, "disk", 0)? {
+            Applied::Committed { revision, op } => Ok(Some((revision, op))),
+            Applied::Duplicate { .. } => Ok(None),
+        }
+    }
+
+    /// True if there are committed edits not yet written to disk
+    pub fn is_dirty(&self) -> bool {
+        self.content != self.disk_content
+    }
+
+    /// Record that the current content was written to disk
+    pub fn mark_saved(&mut self) {
+        self.disk_content = self.content.clone();
+        self.disk_revision = self.revision;
+    }
+
+    /// Drop old revisions, but never ones newer than the last disk sync,
+    /// since merge_disk has to transform against them
+    fn trim_history(&mut self) {
+        let keep_from = self.revision.saturating_sub(HISTORY_LIMIT as u64).min(self.disk_revision);
+        while self.history_start < keep_from {
+            self.history.pop_front();
+            self.history_start += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_an_edit_for_another_length_without_changing_anything() {
+        let mut doc = Document::new("abc".to_string());
+        let mut op = TextOperation::new();
+        op.delete(10);
+        assert!(matches!(doc.apply_edit(0, op, "tab", 1), Err(EditError::Invalid(_))));
+        assert_eq!(doc.content, "abc");
+        assert_eq!(doc.revision, 0);
+    }
+}
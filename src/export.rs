@@ -0,0 +1,222 @@
+use crate::blueprint::{Blueprint, TaskStatus};
+use crate::config::{relative_to, repo_root, Loaded};
+use crate::parser::section_text;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const REGION_BEGIN: &str = "<!-- nira:begin -->";
+const REGION_END: &str = "<!-- nira:end -->";
+
+/// Repository-level instruction files that coding agents pick up automatically
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum AgentTarget {
+    /// AGENTS.md
+    AgentsMd,
+    /// CLAUDE.md
+    ClaudeMd,
+    /// .cursorrules
+    Cursor,
+    /// .github/copilot-instructions.md
+    Copilot,
+}
+
+impl AgentTarget {
+    /// Path of the instruction file, relative to the repository root
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            AgentTarget::AgentsMd => "AGENTS.md",
+            AgentTarget::ClaudeMd => "CLAUDE.md",
+            AgentTarget::Cursor => ".cursorrules",
+            AgentTarget::Copilot => ".github/copilot-instructions.md",
+        }
+    }
+}
+
+/// Write the blueprint summary into the target's instruction file and return its path.
+/// The file goes at the root of the repository holding the blueprint, or next to the
+/// blueprint outside a repository. Only the region between the nira markers is
+/// replaced, so hand-written instructions outside it survive every re-run.
+pub fn export_agents(bp: &Blueprint, target: AgentTarget, config: &Loaded) -> io::Result<PathBuf> {
+    let blueprint = std::path::absolute(&bp.path)?;
+    let dir = blueprint.parent().unwrap_or(Path::new("/"));
+    let absolute = repo_root(dir).unwrap_or(dir).join(target.file_name());
+    let out_path = relative_to(&std::env::current_dir()?, &absolute);
+
+    // Point at the blueprint from where the instruction file is
+    let out_dir = absolute.parent().unwrap_or(Path::new("/"));
+    let region = render_region(bp, &relative_to(out_dir, &blueprint), config);
+    let existing = match std::fs::read_to_string(&out_path) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+
+    let new_content = match existing {
+        Some(content) => replace_region(&content, &region)?,
+        None => region,
+    };
+
+    if let Some(dir) = out_path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&out_path, new_content)?;
+    Ok(out_path)
+}
+
+/// Swap the marked region in `content` for `region`, or append it if there are no markers
+fn replace_region(content: &str, region: &str) -> io::Result<String> {
+    let begin = content.find(REGION_BEGIN);
+    let end = content.find(REGION_END);
+
+    match (begin, end) {
+        (Some(b), Some(e)) if b < e => {
+            let after = e + REGION_END.len();
+            // region already ends with a newline; don't double it
+            let rest = content[after..].strip_prefix('\n').unwrap_or(&content[after..]);
+            Ok(format!("{}{}{}", &content[..b], region, rest))
+        }
+        (None, None) => {
+            let mut result = content.trim_end().to_string();
+            if !result.is_empty() {
+                result.push_str("\n\n");
+            }
+            result.push_str(region);
+            Ok(result)
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Found an unmatched '{}' / '{}' marker - fix it by hand before re-running",
+                REGION_BEGIN, REGION_END
+            ),
+        )),
+    }
+}
+
+/// Build the generated region, markers included
+fn render_region(bp: &Blueprint, blueprint_path: &Path, config: &Loaded) -> String {
+    let mut output = String::new();
+
+    output.push_str(REGION_BEGIN);
+    output.push('\n');
+    output.push_str("<!-- Generated by `nira export agents` from the Blueprint. Edits inside this region are overwritten. -->\n\n");
+
+    match &bp.project_name {
+        Some(name) => output.push_str(&format!("# Architecture: {}\n\n", name)),
+        None => output.push_str("# Architecture\n\n"),
+    }
+    output.push_str(&format!(
+        "This project is designed in `{}`. Read it before changing code.\n\n",
+        display_path(blueprint_path)
+    ));
+
+    // Intent
+    output.push_str("## Intent\n\n");
+    output.push_str(section_text(&bp.raw, "Layer 1: Intent Map").unwrap_or("(not defined yet)"));
+    output.push_str("\n\n");
+
+    // Contracts, names only - the blueprint has the full shapes
+    output.push_str("## Interface Contracts\n\n");
+    let contracts = summarize_contracts(&bp.raw);
+    if contracts.is_empty() {
+        output.push_str("(not defined yet)\n");
+    } else {
+        for (group, names) in &contracts {
+            output.push_str(&format!("- **{}:** {}\n", group, names.join(", ")));
+        }
+    }
+    output.push('\n');
+
+    // Skeleton
+    output.push_str("## File Skeleton\n\n");
+    output.push_str(section_text(&bp.raw, "Layer 3: File Skeleton").unwrap_or("(not defined yet)"));
+    output.push_str("\n\n");
+
+    // Current task
+    output.push_str("## Current Task\n\n");
+    let current = bp
+        .tasks
+        .in_progress
+        .first()
+        .or_else(|| bp.tasks.next_up.first());
+    match current {
+        Some(task) => {
+            let label = match task.status {
+                TaskStatus::InProgress => "in progress",
+                _ => "next up",
+            };
+            output.push_str(&format!("{} ({})\n", task.text, label));
+            if let Some(context) = &task.context {
+                output.push_str(&format!("- Context: {}\n", context));
+            }
+            if let Some(files) = &task.files {
+                output.push_str(&format!("- Files: {}\n", files));
+            }
+        }
+        None => output.push_str("No active tasks.\n"),
+    }
+    output.push('\n');
+
+    // Rules
+    output.push_str("## Rules\n\n");
+    output.push_str(&crate::prompt::full_rules(config));
+    output.push('\n');
+
+    output.push_str(REGION_END);
+    output.push('\n');
+
+    output
+}
+
+/// Collect contract names from Layer 2, grouped by their `###` subsection.
+/// Names come from `####` headings or lines that start with a bold `**Name**`.
+fn summarize_contracts(content: &str) -> Vec<(String, Vec<String>)> {
+    let Some(section) = section_text(content, "Layer 2: Interface Contracts") else {
+        return vec![];
+    };
+
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    let mut in_code_block = false;
+
+    for line in section.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+
+        if let Some(group) = trimmed.strip_prefix("### ") {
+            groups.push((group.trim().to_string(), vec![]));
+            continue;
+        }
+
+        let name = if let Some(rest) = trimmed.strip_prefix("#### ") {
+            Some(rest.trim())
+        } else if let Some(rest) = trimmed.strip_prefix("**") {
+            rest.split("**").next().map(|n| n.trim())
+        } else {
+            None
+        };
+
+        if let Some(name) = name.filter(|n| !n.is_empty()) {
+            if groups.is_empty() {
+                groups.push(("Contracts".to_string(), vec![]));
+            }
+            if let Some((_, names)) = groups.last_mut() {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    groups.retain(|(_, names)| !names.is_empty());
+    groups
+}
+
+/// `path` with forward slashes on every platform
+pub fn display_path(path: &Path) -> String {
+    path.display().to_string().replace('\\', "/")
+}
@@ -0,0 +1,71 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Files touched in the working tree since a given point in history
+pub struct ChangeSet {
+    /// Short commit hash the diff was taken against, or "HEAD" for uncommitted work
+    pub since: String,
+    /// Repository root the file paths are relative to
+    pub root: PathBuf,
+    pub files: Vec<String>,
+}
+
+/// Run git in `dir` and return stdout, or None if git is missing or the command failed
+fn run_git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").current_dir(dir).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Directory to run git from for a given file (its parent, or "." for bare filenames)
+fn repo_dir(file: &Path) -> &Path {
+    match file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Find the commit that last touched `line` (1-indexed) of `file`.
+/// Returns None when the line is not committed yet or git is unavailable.
+fn commit_for_line(file: &Path, line: usize) -> Option<String> {
+    let dir = repo_dir(file);
+    let file_name = file.file_name()?.to_str()?;
+    let range = format!("{},{}", line, line);
+    let blame = run_git(dir, &["blame", "--porcelain", "-L", &range, "--", file_name])?;
+
+    let hash = blame.split_whitespace().next()?;
+    if hash.chars().all(|c| c == '0') {
+        return None;
+    }
+    Some(hash.to_string())
+}
+
+/// List files changed anywhere in the repository since the commit that introduced
+/// `line` of `file`, relative to the repository root.
+/// When the line is uncommitted, changes are relative to HEAD.
+/// Untracked files are included. Returns None outside a git repository.
+pub fn changed_since_line(file: &Path, line: usize) -> Option<ChangeSet> {
+    let root = PathBuf::from(run_git(repo_dir(file), &["rev-parse", "--show-toplevel"])?.trim());
+
+    let base = commit_for_line(file, line).unwrap_or_else(|| "HEAD".to_string());
+
+    let mut files: Vec<String> = Vec::new();
+    if let Some(diff) = run_git(&root, &["diff", "--name-only", &base]) {
+        files.extend(diff.lines().map(|l| l.to_string()));
+    }
+    if let Some(untracked) = run_git(&root, &["ls-files", "--others", "--exclude-standard"]) {
+        files.extend(untracked.lines().map(|l| l.to_string()));
+    }
+    files.sort();
+    files.dedup();
+
+    let since = if base == "HEAD" {
+        base
+    } else {
+        base.chars().take(8).collect()
+    };
+
+    Some(ChangeSet { since, root, files })
+}
@@ -0,0 +1,104 @@
+use serde::Serialize;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Snapshots kept per blueprint unless configured otherwise
+pub const DEFAULT_KEEP: usize = 100;
+
+/// One saved version of a blueprint, stored as `.nira/history/<stem>/<rev>-<time>.md`
+/// next to the blueprint
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    pub rev: u64,
+    /// Local time the snapshot was taken, `YYYY-MM-DD HH:MM:SS`
+    pub time: String,
+    pub lines: usize,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+/// Directory holding the snapshots of `blueprint`
+pub fn history_dir(blueprint: &Path) -> PathBuf {
+    let stem = blueprint
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "blueprint".to_string());
+    let parent = blueprint.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    parent.join(".nira").join("history").join(stem)
+}
+
+/// All snapshots of `blueprint`, oldest first
+pub fn list(blueprint: &Path) -> io::Result<Vec<Snapshot>> {
+    let dir = history_dir(blueprint);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+            continue;
+        };
+        let Some((rev, stamp)) = name.split_once('-') else { continue };
+        let Ok(rev) = rev.parse() else { continue };
+        let time = chrono::NaiveDateTime::parse_from_str(stamp, "%Y%m%dT%H%M%S")
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|_| stamp.to_string());
+        let lines = std::fs::read_to_string(&path).map(|c| c.lines().count()).unwrap_or(0);
+
+        snapshots.push(Snapshot { rev, time, lines, path });
+    }
+    snapshots.sort_by_key(|s| s.rev);
+    Ok(snapshots)
+}
+
+/// Content of snapshot `rev`
+pub fn read(blueprint: &Path, rev: u64) -> io::Result<String> {
+    let snapshot = list(blueprint)?
+        .into_iter()
+        .find(|s| s.rev == rev)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("No snapshot #{}", rev)))?;
+    std::fs::read_to_string(snapshot.path)
+}
+
+/// Store `content` as a new snapshot unless it matches the latest one, then drop
+/// the oldest snapshots beyond `keep` (0 disables history). Returns the new revision.
+pub fn record(blueprint: &Path, content: &str, keep: usize) -> io::Result<Option<u64>> {
+    if keep == 0 {
+        return Ok(None);
+    }
+
+    let snapshots = list(blueprint)?;
+    if let Some(latest) = snapshots.last() {
+        if std::fs::read_to_string(&latest.path).is_ok_and(|c| c == content) {
+            return Ok(None);
+        }
+    }
+
+    let rev = snapshots.last().map_or(1, |s| s.rev + 1);
+    let dir = history_dir(blueprint);
+    std::fs::create_dir_all(&dir)?;
+    let stamp = chrono::Local::now().format("%Y%m%dT%H%M%S");
+    std::fs::write(dir.join(format!("{:06}-{}.md", rev, stamp)), content)?;
+
+    // The new snapshot is not in `snapshots`, so keep one fewer of the old ones
+    let excess = (snapshots.len() + 1).saturating_sub(keep);
+    for old in snapshots.iter().take(excess) {
+        std::fs::remove_file(&old.path)?;
+    }
+
+    Ok(Some(rev))
+}
+
+/// Put snapshot `rev` back into the blueprint file, snapshotting the current
+/// content first so the restore itself can be undone
+pub fn restore(blueprint: &Path, rev: u64, keep: usize) -> io::Result<()> {
+    let content = read(blueprint, rev)?;
+    if let Ok(current) = std::fs::read_to_string(blueprint) {
+        record(blueprint, &current, keep.max(1))?;
+    }
+    std::fs::write(blueprint, &content)?;
+    record(blueprint, &content, keep.max(1))?;
+    Ok(())
+}
@@ -2,23 +2,42 @@ use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 mod blueprint;
+mod clipboard;
+mod config;
+mod diff;
+mod document;
+mod export;
+mod git;
+mod history;
+mod mcp;
+mod migrate;
+mod ot;
 mod parser;
 mod prompt;
+mod render;
 mod server;
 mod tasks;
 mod template;
 mod validator;
+mod wizard;
+mod workspace;
 
 #[derive(Parser)]
 #[command(name = "nira", about = "Your personal architectural control center")]
 struct Cli {
+    /// Print which blueprint and config file a command uses
+    #[arg(long, short = 'v', global = true)]
+    verbose: bool,
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Set from `--verbose` before the command runs
+static VERBOSE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
 #[derive(Subcommand)]
 enum Commands {
-    /// Create a new blueprint.md in the current directory
+    /// Create a new blueprint: niraprint.md in the current directory, unless nira.toml or --output says otherwise
     Init {
         #[arg(long)]
         name: Option<String>,
@@ -30,57 +49,228 @@ enum Commands {
         list_templates: bool,
         #[arg(long)]
         template_file: Option<PathBuf>,
+        /// Value for a template variable, e.g. --var AUTHOR="Ada Lovelace" (repeatable)
+        #[arg(long = "var", value_name = "KEY=VALUE")]
+        vars: Vec<String>,
+        /// Answer a few questions to fill in the intent map and first tasks
+        #[arg(long, short = 'i')]
+        interactive: bool,
+        /// Create the blueprint here instead of niraprint.md (or nira.toml's blueprint)
+        #[arg(long, short = 'o', value_name = "PATH")]
+        output: Option<PathBuf>,
     },
     /// Open blueprint in browser editor with live preview
     Serve {
-        #[arg(default_value = "niraprint.md")]
-        file: PathBuf,
-        #[arg(long, default_value = "3141")]
-        port: u16,
+        file: Option<PathBuf>,
+        /// Port to listen on (default: nira.toml's port, or 3141)
+        #[arg(long)]
+        port: Option<u16>,
+        /// Address to listen on (e.g. 0.0.0.0 to reach the editor from other machines)
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Don't open the editor in a browser
+        #[arg(long)]
+        no_open: bool,
+        /// Serve every blueprint found under this directory, with a sidebar to switch between them
+        #[arg(long, value_name = "DIR", conflicts_with = "file")]
+        workspace: Option<PathBuf>,
+        /// Snapshots to keep per blueprint in .nira/history/ (0 disables history) [default: 100]
+        #[arg(long, value_name = "N")]
+        keep_history: Option<usize>,
     },
     /// Check blueprint completeness
     Validate {
-        #[arg(default_value = "niraprint.md")]
-        file: PathBuf,
+        file: Option<PathBuf>,
     },
     /// Generate AI-ready prompt from blueprint
     Prompt {
-        #[arg(default_value = "niraprint.md")]
-        file: PathBuf,
+        file: Option<PathBuf>,
         #[arg(long)]
         task: Option<usize>,
+        /// Re-ground a fresh agent session on the IN PROGRESS task
+        #[arg(long, conflicts_with = "task")]
+        recover: bool,
+        #[arg(long, value_enum, default_value = "text")]
+        format: prompt::PromptFormat,
+        /// Provider message schema used with --format messages
+        #[arg(long, value_enum, default_value = "openai")]
+        schema: prompt::MessageSchema,
+        /// Assistant prefill appended as the last message (--format messages only)
+        #[arg(long)]
+        prefill: Option<String>,
+        /// Copy the prompt to the clipboard instead of printing it
+        #[arg(long)]
+        clipboard: bool,
+        /// Write the prompt to a file instead of printing it
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
     },
     /// Manage tasks in Layer 4
     Task {
         #[command(subcommand)]
         action: TaskAction,
     },
+    /// Run a Model Context Protocol server on stdio so agents can read and update the blueprint
+    Mcp {
+        file: Option<PathBuf>,
+        /// Only expose read tools; hide start_task, complete_task and propose_contract_change
+        #[arg(long)]
+        read_only: bool,
+    },
+    /// Export the blueprint for other tools
+    Export {
+        #[command(subcommand)]
+        action: ExportAction,
+    },
+    /// List saved snapshots of the blueprint (.nira/history/)
+    History {
+        file: Option<PathBuf>,
+        /// Show what changed between snapshot REV and the current file
+        #[arg(long, value_name = "REV")]
+        diff: Option<u64>,
+    },
+    /// Put a snapshot back into the blueprint (the current content is snapshotted first)
+    Restore {
+        rev: u64,
+        file: Option<PathBuf>,
+    },
+    /// Move a blueprint's content into another template's structure
+    Migrate {
+        file: Option<PathBuf>,
+        /// Template to migrate to
+        #[arg(long, value_name = "TEMPLATE")]
+        to: String,
+        /// Value for a variable of the new template (repeatable)
+        #[arg(long = "var", value_name = "KEY=VALUE")]
+        vars: Vec<String>,
+        /// Write the result here instead of replacing FILE
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
+        /// Print the result instead of writing it
+        #[arg(long, conflicts_with = "output")]
+        dry_run: bool,
+    },
+    /// Manage blueprint templates
+    Template {
+        #[command(subcommand)]
+        action: TemplateAction,
+    },
+    /// Inspect project settings (nira.toml)
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the settings in effect and the file they come from
+    Show,
+}
+
+#[derive(Subcommand)]
+enum TemplateAction {
+    /// List templates with where each one comes from
+    List,
+    /// Print a template
+    Show {
+        name: String,
+        /// Print it with Extends and Include applied
+        #[arg(long)]
+        resolved: bool,
+    },
+    /// Create a template in .nira/templates/ (or your user templates with --user)
+    New {
+        name: String,
+        /// Template to copy as a starting point
+        #[arg(long, default_value = "minimal", conflicts_with = "extends")]
+        from: String,
+        /// Start with an empty template that extends BASE instead of a copy
+        #[arg(long, value_name = "BASE")]
+        extends: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+        #[arg(long)]
+        user: bool,
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check that templates resolve, match their schema and have no dangling placeholders
+    Lint {
+        /// Template to check (all templates when omitted)
+        #[arg(conflicts_with = "file")]
+        name: Option<String>,
+        /// Check a template file outside the search path
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Save a blueprint as a reusable template, putting placeholders back in
+    Save {
+        name: String,
+        file: Option<PathBuf>,
+        #[arg(long)]
+        description: Option<String>,
+        /// Turn VALUE back into a {KEY} variable (repeatable)
+        #[arg(long = "var", value_name = "KEY=VALUE")]
+        vars: Vec<String>,
+        #[arg(long)]
+        user: bool,
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExportAction {
+    /// Write a marked region into an agent instruction file (AGENTS.md, CLAUDE.md, ...)
+    Agents {
+        #[arg(long, value_enum)]
+        target: export::AgentTarget,
+        file: Option<PathBuf>,
+    },
 }
 
 #[derive(Subcommand)]
 enum TaskAction {
     /// List all tasks
     List {
-        #[arg(default_value = "niraprint.md")]
-        file: PathBuf,
+        file: Option<PathBuf>,
     },
     /// Add a task to NEXT UP
     Add {
         description: String,
-        #[arg(default_value = "niraprint.md")]
-        file: PathBuf,
+        file: Option<PathBuf>,
     },
     /// Move a task to DONE
     Done {
         task_num: usize,
-        #[arg(default_value = "niraprint.md")]
-        file: PathBuf,
+        file: Option<PathBuf>,
     },
     /// Move a task to IN PROGRESS
     Start {
         task_num: usize,
-        #[arg(default_value = "niraprint.md")]
-        file: PathBuf,
+        file: Option<PathBuf>,
+    },
+    /// Move a task to any section
+    Move {
+        task_num: usize,
+        #[arg(value_enum)]
+        to: blueprint::TaskStatus,
+        file: Option<PathBuf>,
+    },
+    /// Change a task's text or metadata
+    Edit {
+        task_num: usize,
+        /// New task description
+        #[arg(long)]
+        text: Option<String>,
+        #[arg(long)]
+        context: Option<String>,
+        #[arg(long)]
+        files: Option<String>,
+        #[arg(long)]
+        approach: Option<String>,
+        file: Option<PathBuf>,
     },
 }
 
@@ -88,6 +278,15 @@ enum TaskAction {
 async fn main() {
     let cli = Cli::parse();
 
+    let config = match std::env::current_dir().and_then(|cwd| config::load(&cwd)) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: Invalid config {}", e);
+            std::process::exit(1);
+        }
+    };
+    VERBOSE.store(cli.verbose, std::sync::atomic::Ordering::Relaxed);
+
     match cli.command {
         Commands::Init {
             name,
@@ -95,34 +294,48 @@ async fn main() {
             template: template_name,
             list_templates,
             template_file,
+            vars,
+            interactive: wizard,
+            output,
         } => {
             // List available templates if requested
             if list_templates {
-                println!("Available templates:\n");
-                for (name, description) in template::list_templates() {
-                    println!("  {} - {}", name, description);
-                }
-                println!("\nUsage: nira init --template <name>");
+                print_templates(&config);
                 return;
             }
 
-            let file_path = std::path::Path::new("niraprint.md");
+            let file_path = output.unwrap_or_else(|| config.new_blueprint());
 
             // Check if file already exists
             if file_path.exists() && !force {
-                eprintln!("Error: niraprint.md already exists in this directory.");
+                eprintln!("Error: {} already exists.", file_path.display());
                 eprintln!("Use --force to overwrite it.");
                 std::process::exit(1);
             }
 
             // Get template
-            let template_str = match template::get_template(&template_name) {
-                Some(t) => t,
+            let found = match &template_file {
+                Some(path) => match template::from_file(path) {
+                    Ok(t) => Some(t),
+                    Err(e) => {
+                        eprintln!("Error: Could not read template file {}: {}", path.display(), e);
+                        std::process::exit(1);
+                    }
+                },
+                None => template::find_template(&template_name, config.project_root()),
+            };
+            let template_name = found.as_ref().map_or(template_name, |t| t.name.clone());
+            let template_str = match found.map(|t| template::resolve(&t, config.project_root())) {
+                Some(Ok(t)) => t,
+                Some(Err(e)) => {
+                    eprintln!("Error: Template '{}': {}", template_name, e);
+                    std::process::exit(1);
+                }
                 None => {
                     eprintln!("Error: Template '{}' not found.", template_name);
                     eprintln!("\nAvailable templates:");
-                    for (name, description) in template::list_templates() {
-                        eprintln!("  {} - {}", name, description);
+                    for t in template::list_templates(config.project_root()) {
+                        eprintln!("  {} - {}", t.name, t.description);
                     }
                     std::process::exit(1);
                 }
@@ -140,32 +353,139 @@ async fn main() {
             // Get current date
             let date = chrono::Local::now().format("%Y-%m-%d").to_string();
 
+            let mut values = std::collections::HashMap::from([
+                ("PROJECT_NAME".to_string(), project_name),
+                ("DATE".to_string(), date),
+            ]);
+            values.extend(parse_vars(&vars));
+
+            // Ask for declared variables that weren't given, or fall back to their defaults
+            let interactive = std::io::IsTerminal::is_terminal(&std::io::stdin());
+            for variable in template::variables(&template_str) {
+                if values.contains_key(&variable.name) {
+                    continue;
+                }
+                let value = if interactive {
+                    ask_variable(&variable)
+                } else if let Some(default) = &variable.default {
+                    default.clone()
+                } else {
+                    eprintln!(
+                        "Error: Template '{}' needs {} ({}). Pass --var {}=...",
+                        template_name, variable.name, variable.description, variable.name
+                    );
+                    std::process::exit(1);
+                };
+                values.insert(variable.name, value);
+            }
+
             // Substitute placeholders in template
-            let content = template_str
-                .replace("{PROJECT_NAME}", &project_name)
-                .replace("{DATE}", &date);
+            let mut content = match template::render(&template_str, &values) {
+                Ok(content) => content,
+                Err(e) => {
+                    eprintln!("Error: Template '{}': {}", template_name, e);
+                    std::process::exit(1);
+                }
+            };
+
+            if wizard {
+                if parser::find_section(&content, "Layer 1: Intent Map").is_none() {
+                    eprintln!("Error: Template '{}' has no intent map to fill in.", template_name);
+                    eprintln!("Use --interactive with a layered template such as default or minimal.");
+                    std::process::exit(1);
+                }
+                content = match wizard::ask().and_then(|intent| wizard::apply(&content, &intent)) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                };
+            }
 
             // Write file
-            match std::fs::write(file_path, content) {
+            let written = match file_path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => std::fs::create_dir_all(dir),
+                _ => Ok(()),
+            }
+            .and_then(|_| std::fs::write(&file_path, content));
+            match written {
                 Ok(_) => {
-                    println!("✓ Created niraprint.md using '{}' template", template_name);
+                    println!("✓ Created {} using '{}' template", file_path.display(), template_name);
+                    let found = config.find_blueprint();
+                    let same = |a: &std::path::Path, b: &std::path::Path| {
+                        std::fs::canonicalize(a).ok().is_some_and(|a| std::fs::canonicalize(b).is_ok_and(|b| a == b))
+                    };
+                    let is_default = same(&found.path, &file_path);
+                    if !is_default {
+                        println!(
+                            "\nCommands run here without a FILE use {}. Pass {} to them, or set blueprint = \"{}\" in {}.",
+                            found.path.display(),
+                            file_path.display(),
+                            file_path.display(),
+                            config::FILE_NAME
+                        );
+                    } else if !found.passed_over.is_empty() {
+                        warn_ambiguous(&found);
+                    }
                     println!("\nNext steps:");
-                    println!("  1. Edit niraprint.md and fill in the layers");
-                    println!("  2. Run 'nira validate' to check your progress");
-                    println!("  3. Run 'nira prompt' to generate AI instructions");
+                    if wizard {
+                        println!("  1. Edit {} and fill in layers 2-4", file_path.display());
+                    } else {
+                        println!("  1. Edit {} and fill in the layers", file_path.display());
+                    }
+                    // Name the file in the commands when they wouldn't find it on their own
+                    let file_arg = if is_default { String::new() } else { format!(" {}", file_path.display()) };
+                    println!("  2. Run 'nira validate{}' to check your progress", file_arg);
+                    println!("  3. Run 'nira prompt{}' to generate AI instructions", file_arg);
                 }
                 Err(e) => {
-                    eprintln!("Error: Failed to write niraprint.md: {}", e);
+                    eprintln!("Error: Failed to write {}: {}", file_path.display(), e);
                     std::process::exit(1);
                 }
             }
         }
-        Commands::Serve { file, port } => {
-            server::serve(file, port).await;
+        Commands::Serve {
+            file,
+            port,
+            host,
+            no_open,
+            workspace,
+            keep_history,
+        } => {
+            let options = server::ServeOptions {
+                host,
+                port: config.port(port),
+                open: !no_open,
+                history_keep: config.keep_history(keep_history),
+                config: config.clone(),
+            };
+            match workspace {
+                Some(dir) => {
+                    let files = match workspace::discover(&dir, &config) {
+                        Ok(files) => files,
+                        Err(e) => {
+                            eprintln!("Error: Failed to read {}: {}", dir.display(), e);
+                            std::process::exit(1);
+                        }
+                    };
+                    if files.is_empty() {
+                        eprintln!("Error: No blueprints found under {}", dir.display());
+                        std::process::exit(1);
+                    }
+                    server::serve(&dir, files, options).await;
+                }
+                None => {
+                    let file = blueprint_file(&config, file);
+                    let root = file.parent().map(PathBuf::from).unwrap_or_default();
+                    server::serve(&root, vec![file], options).await;
+                }
+            }
         }
         Commands::Validate { file } => {
-            let bp = load_blueprint(&file);
-            let results = validator::validate(&bp);
+            let file = blueprint_file(&config, file);
+            let bp = load_blueprint(&config, &file);
+            let results = validator::validate(&bp, &config);
 
             println!("Blueprint Validation: {}\n", file.display());
 
@@ -180,6 +500,9 @@ async fn main() {
                     "{} Layer {}: {} - {}",
                     symbol, result.layer, result.layer_name, result.message
                 );
+                if let Some(fix) = &result.fix {
+                    println!("    → {}", fix);
+                }
             }
 
             // Exit with error code if there are Missing items
@@ -190,11 +513,30 @@ async fn main() {
                 std::process::exit(1);
             }
         }
-        Commands::Prompt { file, task } => {
-            let bp = load_blueprint(&file);
+        Commands::Prompt {
+            file,
+            task,
+            recover,
+            format,
+            schema,
+            prefill,
+            clipboard,
+            output: output_file,
+        } => {
+            let file = blueprint_file(&config, file);
+            let bp = load_blueprint(&config, &file);
 
-            let output = if let Some(task_num) = task {
-                match prompt::generate_for_task(&bp, task_num) {
+            let output = if recover {
+                match prompt::generate_recovery(&bp, &config) {
+                    Some(prompt) => prompt,
+                    None => {
+                        eprintln!("Error: No task is IN PROGRESS.");
+                        eprintln!("Run 'nira task start <N>' to pick the task you were working on.");
+                        std::process::exit(1);
+                    }
+                }
+            } else if let Some(task_num) = task {
+                match prompt::generate_for_task(&bp, task_num, &config) {
                     Some(prompt) => prompt,
                     None => {
                         eprintln!("Error: Task #{} not found.", task_num);
@@ -203,26 +545,64 @@ async fn main() {
                     }
                 }
             } else {
-                prompt::generate(&bp)
+                prompt::generate(&bp, &config)
+            };
+
+            let rendered = match format {
+                prompt::PromptFormat::Text => output.to_string(),
+                prompt::PromptFormat::Messages => {
+                    let messages = output.to_messages(schema, prefill.as_deref());
+                    serde_json::to_string_pretty(&messages).unwrap()
+                }
             };
 
-            println!("{}", output);
+            if !clipboard && output_file.is_none() {
+                println!("{}", rendered);
+                return;
+            }
+
+            let tokens = prompt::approx_tokens(&rendered);
+
+            if let Some(path) = output_file {
+                if let Err(e) = std::fs::write(&path, &rendered) {
+                    eprintln!("Error: Failed to write {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+                println!("✓ Wrote prompt to {} (~{} tokens)", path.display(), tokens);
+            }
+
+            if clipboard {
+                match clipboard::copy(&rendered) {
+                    Ok(clipboard::CopyMethod::Tool(tool)) => {
+                        println!("✓ Copied prompt to clipboard via {} (~{} tokens)", tool, tokens);
+                    }
+                    Ok(clipboard::CopyMethod::Osc52) => {
+                        println!("✓ Sent prompt to the terminal clipboard (OSC 52, ~{} tokens)", tokens);
+                    }
+                    Err(e) => {
+                        eprintln!("Error: Failed to copy to clipboard: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
         }
         Commands::Task { action } => match action {
             TaskAction::List { file } => {
-                let bp = load_blueprint(&file);
+                let file = blueprint_file(&config, file);
+                let bp = load_blueprint(&config, &file);
                 tasks::list_tasks(&bp);
             }
-            TaskAction::Add { description, file } => match tasks::add_task(&file, &description) {
-                Ok(_) => {}
+            TaskAction::Add { description, file } => match tasks::add_task(&blueprint_file(&config, file), &description) {
+                Ok(_) => println!("✓ Added task to NEXT UP: {}", description),
                 Err(e) => {
                     eprintln!("Error: {}", e);
                     std::process::exit(1);
                 }
             },
             TaskAction::Done { task_num, file } => {
+                let file = blueprint_file(&config, file);
                 match tasks::move_task(&file, task_num, blueprint::TaskStatus::Done) {
-                    Ok(_) => {}
+                    Ok(task) => print_moved(task_num, blueprint::TaskStatus::Done, &task),
                     Err(e) => {
                         eprintln!("Error: {}", e);
                         std::process::exit(1);
@@ -230,8 +610,289 @@ async fn main() {
                 }
             }
             TaskAction::Start { task_num, file } => {
+                let file = blueprint_file(&config, file);
                 match tasks::move_task(&file, task_num, blueprint::TaskStatus::InProgress) {
-                    Ok(_) => {}
+                    Ok(task) => print_moved(task_num, blueprint::TaskStatus::InProgress, &task),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            TaskAction::Move { task_num, to, file } => match tasks::move_task(&blueprint_file(&config, file), task_num, to) {
+                Ok(task) => print_moved(task_num, to, &task),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            TaskAction::Edit {
+                task_num,
+                text,
+                context,
+                files,
+                approach,
+                file,
+            } => {
+                let file = blueprint_file(&config, file);
+                let update = tasks::TaskUpdate {
+                    text,
+                    context,
+                    files,
+                    approach,
+                };
+                match tasks::edit_task(&file, task_num, &update) {
+                    Ok(task) => println!("✓ Updated task #{}: {}", task_num, task.text),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        },
+        Commands::History { file, diff } => {
+            let file = blueprint_file(&config, file);
+            let result = match diff {
+                Some(rev) => print_snapshot_diff(&file, rev),
+                None => print_history(&file),
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Restore { rev, file } => {
+            let file = blueprint_file(&config, file);
+            match history::restore(&file, rev, config.keep_history(None)) {
+                Ok(()) => println!("✓ Restored {} to snapshot #{}", file.display(), rev),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Migrate {
+            file,
+            to,
+            vars,
+            output,
+            dry_run,
+        } => {
+            let file = blueprint_file(&config, file);
+            let source = match std::fs::read_to_string(&file) {
+                Ok(c) => c,
+                Err(_) => {
+                    eprintln!("Error: Could not read {}.", file.display());
+                    std::process::exit(1);
+                }
+            };
+            let target = find_template_or_exit(&to, &config);
+            let resolved = match template::resolve(&target, config.project_root()) {
+                Ok(resolved) => resolved,
+                Err(e) => {
+                    eprintln!("Error: Template '{}': {}", to, e);
+                    std::process::exit(1);
+                }
+            };
+            let schema = template::schema(&resolved).unwrap_or_else(|e| {
+                eprintln!("Error: Template '{}': {}", to, e);
+                std::process::exit(1);
+            });
+
+            // Keep the old blueprint's name and start date; variables nobody gave a value
+            // stay visible as [placeholders]
+            let date = regex::Regex::new(r"\d{4}-\d{2}-\d{2}")
+                .unwrap()
+                .find(source.split("\n## ").next().unwrap_or(""))
+                .map(|m| m.as_str().to_string())
+                .unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+            let project_name = parser::title_project_name(&source).unwrap_or_else(|| "MyProject".to_string());
+            let mut values = std::collections::HashMap::from([
+                ("PROJECT_NAME".to_string(), project_name),
+                ("DATE".to_string(), date),
+            ]);
+            values.extend(parse_vars(&vars));
+            for variable in template::variables(&resolved) {
+                let fallback = variable.default.unwrap_or_else(|| format!("[{}]", variable.description.to_lowercase()));
+                values.entry(variable.name).or_insert(fallback);
+            }
+            let rendered = match template::render(&resolved, &values) {
+                Ok(rendered) => rendered,
+                Err(e) => {
+                    eprintln!("Error: Template '{}': {}", to, e);
+                    std::process::exit(1);
+                }
+            };
+
+            let migration = migrate::migrate(&source, &rendered, schema);
+            if dry_run {
+                print!("{}", migration.content);
+                return;
+            }
+
+            let destination = output.unwrap_or_else(|| file.clone());
+            if destination == file {
+                // `nira restore` can bring the old structure back
+                if let Err(e) = history::record(&file, &source, config.keep_history(None)) {
+                    eprintln!("Error: Could not snapshot {} before migrating: {}", file.display(), e);
+                    std::process::exit(1);
+                }
+            }
+            if let Err(e) = std::fs::write(&destination, &migration.content) {
+                eprintln!("Error: Failed to write {}: {}", destination.display(), e);
+                std::process::exit(1);
+            }
+
+            println!("✓ Migrated {} to the '{}' template ({})", file.display(), to, destination.display());
+            for mapping in &migration.mappings {
+                match &mapping.to {
+                    Some(to) => println!("  {} → {}", mapping.from, to),
+                    None => println!("  {} → Unmapped", mapping.from),
+                }
+            }
+            for section in &migration.missing {
+                println!("⚠ The result has no '{}' section, which the {} schema requires", section, schema);
+            }
+            if destination == file {
+                println!("\nThe previous version is in the history: nira history {}", file.display());
+            }
+        }
+        Commands::Config { action } => match action {
+            ConfigAction::Show => {
+                match &config.source {
+                    Some(path) => println!("# Settings from {}", path.display()),
+                    None => println!("# No {} found, using the defaults", config::FILE_NAME),
+                }
+                println!();
+                print!("{}", config.effective());
+            }
+        },
+        Commands::Template { action } => match action {
+            TemplateAction::List => print_templates(&config),
+            TemplateAction::Show { name, resolved } => {
+                let t = find_template_or_exit(&name, &config);
+                if resolved {
+                    match template::resolve(&t, config.project_root()) {
+                        Ok(content) => print!("{}", content),
+                        Err(e) => {
+                            eprintln!("Error: Template '{}': {}", name, e);
+                            std::process::exit(1);
+                        }
+                    }
+                } else {
+                    eprintln!("{}", template::describe_origin(&t));
+                    print!("{}", t.content);
+                }
+            }
+            TemplateAction::New {
+                name,
+                from,
+                extends,
+                description,
+                user,
+                force,
+            } => {
+                let content = match &extends {
+                    Some(base) => {
+                        find_template_or_exit(base, &config);
+                        let description = description.unwrap_or_else(|| format!("{} with our own sections", base));
+                        format!("<!-- Description: {} -->\n<!-- Extends: {} -->\n\n", description, base)
+                    }
+                    None => {
+                        let base = find_template_or_exit(&from, &config);
+                        let description = description.unwrap_or_else(|| format!("Based on {}", from));
+                        let body: Vec<&str> =
+                            base.content.lines().filter(|l| !l.trim().starts_with("<!-- Description:")).collect();
+                        format!("<!-- Description: {} -->\n{}\n", description, body.join("\n"))
+                    }
+                };
+                let path = write_template(&name, &content, user, force, &config);
+                println!("✓ Created {}", path.display());
+                println!("\nEdit it, then run 'nira template lint {}' and 'nira init --template {}'", name, name);
+            }
+            TemplateAction::Lint { name, file } => {
+                let templates = match (name, file) {
+                    (_, Some(path)) => match template::from_file(&path) {
+                        Ok(t) => vec![t],
+                        Err(e) => {
+                            eprintln!("Error: Could not read template file {}: {}", path.display(), e);
+                            std::process::exit(1);
+                        }
+                    },
+                    (Some(name), None) => vec![find_template_or_exit(&name, &config)],
+                    (None, None) => template::list_templates(config.project_root()),
+                };
+
+                let mut errors = 0;
+                for t in &templates {
+                    let findings = template::lint(t, config.project_root());
+                    if findings.is_empty() {
+                        println!("✓ {}", t.name);
+                        continue;
+                    }
+                    println!("{}", t.name);
+                    for finding in findings {
+                        let symbol = match finding.severity {
+                            blueprint::Severity::Error => {
+                                errors += 1;
+                                "✗"
+                            }
+                            blueprint::Severity::Warning => "⚠",
+                        };
+                        println!("  {} {}", symbol, finding.message);
+                    }
+                }
+                if errors > 0 {
+                    std::process::exit(1);
+                }
+            }
+            TemplateAction::Save {
+                name,
+                file,
+                description,
+                vars,
+                user,
+                force,
+            } => {
+                let file = blueprint_file(&config, file);
+                let content = match std::fs::read_to_string(&file) {
+                    Ok(c) => c,
+                    Err(_) => {
+                        eprintln!("Error: Could not read {}.", file.display());
+                        std::process::exit(1);
+                    }
+                };
+                let replacements = parse_vars(&vars);
+                // An empty value would match between every pair of characters
+                if let Some((key, _)) = replacements.iter().find(|(_, value)| value.trim().is_empty()) {
+                    eprintln!("Error: --var {} needs the text to turn into {{{}}}, got an empty value", key, key);
+                    std::process::exit(1);
+                }
+                let description = description.unwrap_or_else(|| format!("Saved from {}", file.display()));
+                let content = template::from_blueprint(&content, &description, &replacements);
+                let path = write_template(&name, &content, user, force, &config);
+                println!("✓ Saved {} as template '{}' ({})", file.display(), name, path.display());
+
+                // Text that happened to look like a placeholder can't be instantiated
+                if let Ok(t) = template::from_file(&path) {
+                    for finding in template::lint(&t, config.project_root()) {
+                        println!("  ⚠ {}", finding.message);
+                    }
+                }
+            }
+        },
+        Commands::Mcp { file, read_only } => {
+            if let Err(e) = mcp::run(blueprint_file(&config, file), read_only, config) {
+                eprintln!("Error: MCP server failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Export { action } => match action {
+            ExportAction::Agents { target, file } => {
+                let file = blueprint_file(&config, file);
+                let bp = load_blueprint(&config, &file);
+                match export::export_agents(&bp, target, &config) {
+                    Ok(path) => println!("✓ Updated {}", export::display_path(&path)),
                     Err(e) => {
                         eprintln!("Error: {}", e);
                         std::process::exit(1);
@@ -242,15 +903,210 @@ async fn main() {
     }
 }
 
+/// Helper: report a task move the same way for every command
+fn print_moved(task_num: usize, target: blueprint::TaskStatus, task: &blueprint::TaskItem) {
+    println!("✓ Moved task #{} to {}: {}", task_num, target.section_name(), task.text);
+}
+
+/// Helper: the blueprint a command works on, from its FILE argument or found by
+/// walking up from the current directory
+fn blueprint_file(config: &config::Loaded, file: Option<PathBuf>) -> PathBuf {
+    let file = file.unwrap_or_else(|| {
+        let found = config.find_blueprint();
+        if !found.passed_over.is_empty() {
+            warn_ambiguous(&found);
+        }
+        found.path
+    });
+    if VERBOSE.load(std::sync::atomic::Ordering::Relaxed) {
+        eprintln!("Blueprint: {}", file.display());
+        if let Some(source) = &config.source {
+            eprintln!("Config: {}", source.display());
+        }
+    }
+    file
+}
+
+/// Helper: say which of several blueprints in one directory is used
+fn warn_ambiguous(found: &config::Found) {
+    let others: Vec<String> = found.passed_over.iter().map(|p| p.display().to_string()).collect();
+    eprintln!(
+        "Warning: {} and {} are both blueprints; using {}.",
+        found.path.display(),
+        others.join(" and "),
+        found.path.display()
+    );
+    eprintln!(
+        "Pass the file to use, or set blueprint = \"...\" in {} to silence this.",
+        config::FILE_NAME
+    );
+}
+
 /// Helper: load and parse a blueprint file, or exit with a nice error
-fn load_blueprint(path: &PathBuf) -> blueprint::Blueprint {
+fn load_blueprint(config: &config::Loaded, path: &PathBuf) -> blueprint::Blueprint {
     let content = match std::fs::read_to_string(path) {
         Ok(c) => c,
+        Err(_) if !path.exists() && path == std::path::Path::new(config.filenames()[0]) => {
+            eprintln!(
+                "Error: No {} in this directory or its parents (up to the repository root).",
+                config.filenames().join(" or ")
+            );
+            eprintln!("Run 'nira init' to create one");
+            std::process::exit(1);
+        }
         Err(_) => {
             eprintln!("Error: Could not read {}.", path.display());
-            eprintln!("Run 'nira init' to create niraprint.md");
+            if !path.exists() {
+                eprintln!("Run 'nira init --output {}' to create it", path.display());
+            }
             std::process::exit(1);
         }
     };
     parser::parse(&content, path.clone())
 }
+
+/// Helper: parse repeated `--var KEY=VALUE` arguments
+fn parse_vars(vars: &[String]) -> Vec<(String, String)> {
+    vars.iter()
+        .map(|var| match var.split_once('=') {
+            Some((key, value)) => (key.trim().to_string(), value.to_string()),
+            None => {
+                eprintln!("Error: --var expects KEY=VALUE, got '{}'", var);
+                std::process::exit(1);
+            }
+        })
+        .collect()
+}
+
+/// Helper: print every template with its description and where it comes from
+fn print_templates(config: &config::Loaded) {
+    println!("Available templates:\n");
+    for t in template::list_templates(config.project_root()) {
+        println!("  {} - {} {}", t.name, t.description, template::describe_origin(&t));
+    }
+    println!("\nUsage: nira init --template <name>");
+}
+
+/// Helper: look up a template by name or exit with an error
+fn find_template_or_exit(name: &str, config: &config::Loaded) -> template::Template {
+    template::find_template(name, config.project_root()).unwrap_or_else(|| {
+        eprintln!("Error: Template '{}' not found.", name);
+        eprintln!("Run 'nira template list' to see the available templates");
+        std::process::exit(1);
+    })
+}
+
+/// Helper: write a template into the project (or user) template directory
+fn write_template(name: &str, content: &str, user: bool, force: bool, config: &config::Loaded) -> PathBuf {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        eprintln!("Error: '{}' is not a valid template name", name);
+        std::process::exit(1);
+    }
+    let dir = if user {
+        template::user_dir().unwrap_or_else(|| {
+            eprintln!("Error: Neither XDG_CONFIG_HOME nor HOME is set");
+            std::process::exit(1);
+        })
+    } else {
+        template::project_dir(config.project_root())
+    };
+
+    let path = dir.join(format!("{}.md", name));
+    if path.exists() && !force {
+        eprintln!("Error: {} already exists.", path.display());
+        eprintln!("Use --force to overwrite it.");
+        std::process::exit(1);
+    }
+    if let Err(e) = std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, content)) {
+        eprintln!("Error: Failed to write {}: {}", path.display(), e);
+        std::process::exit(1);
+    }
+    path
+}
+
+/// Helper: list snapshots, newest first
+fn print_history(file: &std::path::Path) -> std::io::Result<()> {
+    let snapshots = history::list(file)?;
+    if snapshots.is_empty() {
+        println!("No history for {} yet. Snapshots are taken when 'nira serve' saves.", file.display());
+        return Ok(());
+    }
+
+    println!("History for: {}\n", file.display());
+    for snapshot in snapshots.iter().rev() {
+        println!("  #{:<5} {}  {} lines", snapshot.rev, snapshot.time, snapshot.lines);
+    }
+    println!("\nRun 'nira history --diff REV' to compare, 'nira restore REV' to go back.");
+    Ok(())
+}
+
+/// Helper: print a snapshot's changes up to the current file as a line diff
+fn print_snapshot_diff(file: &std::path::Path, rev: u64) -> std::io::Result<()> {
+    let old = history::read(file, rev)?;
+    let current = std::fs::read_to_string(file)?;
+
+    println!("--- snapshot #{}\n+++ {}", rev, file.display());
+    let mut line_no = 1;
+    let mut in_change = false;
+    for line in diff::diff_lines(&old, &current) {
+        if !matches!(line, diff::DiffLine::Same(_)) && !in_change {
+            println!("@@ line {} @@", line_no);
+        }
+        in_change = !matches!(line, diff::DiffLine::Same(_));
+        match line {
+            diff::DiffLine::Same(_) => line_no += 1,
+            diff::DiffLine::Removed(text) => print!("-{}", with_newline(&text)),
+            diff::DiffLine::Added(text) => {
+                print!("+{}", with_newline(&text));
+                line_no += 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn with_newline(line: &str) -> String {
+    if line.ends_with('\n') {
+        line.to_string()
+    } else {
+        format!("{}\n", line)
+    }
+}
+
+/// Prompt on the terminal for a template variable until it gets an acceptable value;
+/// an empty answer takes the default
+fn ask_variable(variable: &template::Variable) -> String {
+    use std::io::Write;
+
+    loop {
+        print!("{}", variable.description);
+        if !variable.choices.is_empty() {
+            print!(" ({})", variable.choices.join("/"));
+        }
+        if let Some(default) = &variable.default {
+            print!(" [{}]", default);
+        }
+        print!(": ");
+        let _ = std::io::stdout().flush();
+
+        let mut answer = String::new();
+        match std::io::stdin().read_line(&mut answer) {
+            Ok(0) | Err(_) => {
+                eprintln!("\nError: No value for {}", variable.name);
+                std::process::exit(1);
+            }
+            Ok(_) => {}
+        }
+
+        let answer = answer.trim();
+        let value = match (&variable.default, answer.is_empty()) {
+            (Some(default), true) => default.clone(),
+            (None, true) => continue,
+            (_, false) => answer.to_string(),
+        };
+        if variable.choices.is_empty() || variable.choices.contains(&value) {
+            return value;
+        }
+        println!("  Choose one of: {}", variable.choices.join(", "));
+    }
+}
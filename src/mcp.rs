@@ -0,0 +1,389 @@
+use crate::blueprint::{Blueprint, TaskStatus};
+use crate::config::Loaded;
+use crate::parser::{find_section, section_text};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+const SUPPORTED_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const LAYERS: &[(u8, &str)] = &[
+    (1, "Layer 1: Intent Map"),
+    (2, "Layer 2: Interface Contracts"),
+    (3, "Layer 3: File Skeleton"),
+    (4, "Layer 4: Task Queue"),
+];
+
+/// Tools that change the blueprint on disk
+const MUTATING_TOOLS: &[&str] = &["start_task", "complete_task", "propose_contract_change"];
+
+struct McpServer {
+    file: PathBuf,
+    read_only: bool,
+    config: Loaded,
+}
+
+/// Run a Model Context Protocol server over stdio (newline-delimited JSON-RPC 2.0).
+/// stdout carries protocol messages only; diagnostics go to stderr.
+pub fn run(file: PathBuf, read_only: bool, config: Loaded) -> io::Result<()> {
+    let server = McpServer { file, read_only, config };
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(request) => server.handle(&request),
+            Err(e) => Some(error_response(Value::Null, -32700, &format!("Parse error: {}", e))),
+        };
+
+        if let Some(response) = response {
+            writeln!(stdout, "{}", response)?;
+            stdout.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+impl McpServer {
+    /// Handle one JSON-RPC message. Notifications (no id) get no response.
+    fn handle(&self, request: &Value) -> Option<Value> {
+        let id = request.get("id").cloned();
+        let method = request.get("method").and_then(|m| m.as_str()).unwrap_or("");
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        let id = id?;
+
+        let result = match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "ping" => Ok(json!({})),
+            "resources/list" => self.list_resources(),
+            "resources/read" => self.read_resource(&params),
+            "tools/list" => Ok(self.list_tools()),
+            "tools/call" => self.call_tool(&params),
+            _ => Err((-32601, format!("Method not found: {}", method))),
+        };
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    fn initialize(&self, params: &Value) -> Value {
+        let requested = params.get("protocolVersion").and_then(|v| v.as_str());
+        let version = requested
+            .filter(|v| SUPPORTED_VERSIONS.contains(v))
+            .unwrap_or(SUPPORTED_VERSIONS[0]);
+
+        json!({
+            "protocolVersion": version,
+            "capabilities": {
+                "resources": {},
+                "tools": {},
+            },
+            "serverInfo": {
+                "name": "nira",
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "instructions": "The Blueprint is the source of truth for this project's architecture. \
+                Read it before writing code, work on one task at a time, and propose contract \
+                changes instead of editing interfaces directly.",
+        })
+    }
+
+    fn load(&self) -> Result<Blueprint, (i64, String)> {
+        let content = std::fs::read_to_string(&self.file).map_err(|e| {
+            (-32603, format!("Could not read {}: {}", self.file.display(), e))
+        })?;
+        Ok(crate::parser::parse(&content, self.file.clone()))
+    }
+
+    fn list_resources(&self) -> Result<Value, (i64, String)> {
+        let bp = self.load()?;
+        let mut resources = vec![json!({
+            "uri": "nira://blueprint",
+            "name": "Blueprint",
+            "description": format!("The full blueprint ({})", self.file.display()),
+            "mimeType": "text/markdown",
+        })];
+
+        for (num, heading) in LAYERS {
+            resources.push(json!({
+                "uri": format!("nira://layer/{}", num),
+                "name": heading,
+                "mimeType": "text/markdown",
+            }));
+        }
+
+        for (num, task) in bp.tasks.all_active_numbered() {
+            resources.push(json!({
+                "uri": format!("nira://task/{}", num),
+                "name": format!("Task #{}: {}", num, task.text),
+                "mimeType": "application/json",
+            }));
+        }
+
+        Ok(json!({ "resources": resources }))
+    }
+
+    fn read_resource(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let uri = params
+            .get("uri")
+            .and_then(|u| u.as_str())
+            .ok_or((-32602, "Missing 'uri'".to_string()))?;
+        let bp = self.load()?;
+
+        let (mime, text) = if uri == "nira://blueprint" {
+            ("text/markdown", bp.raw.clone())
+        } else if let Some(num) = uri.strip_prefix("nira://layer/") {
+            let heading = num
+                .parse::<u8>()
+                .ok()
+                .and_then(|n| LAYERS.iter().find(|(l, _)| *l == n))
+                .map(|(_, h)| *h)
+                .ok_or((-32602, format!("Unknown layer: {}", num)))?;
+            let text = section_text(&bp.raw, heading)
+                .ok_or((-32602, format!("'{}' is not in the blueprint", heading)))?;
+            ("text/markdown", text.to_string())
+        } else if let Some(num) = uri.strip_prefix("nira://task/") {
+            let task_num = num.parse::<usize>().unwrap_or(0);
+            let active = bp.tasks.all_active_numbered();
+            let (_, task) = active
+                .iter()
+                .find(|(n, _)| *n == task_num)
+                .ok_or((-32602, format!("Task #{} not found", num)))?;
+            ("application/json", serde_json::to_string_pretty(task).unwrap_or_default())
+        } else {
+            return Err((-32602, format!("Unknown resource: {}", uri)));
+        };
+
+        Ok(json!({
+            "contents": [{ "uri": uri, "mimeType": mime, "text": text }]
+        }))
+    }
+
+    fn list_tools(&self) -> Value {
+        let task_num = json!({
+            "type": "object",
+            "properties": {
+                "task": { "type": "integer", "description": "Task number from list_tasks" },
+                "confirm": { "type": "boolean", "description": "Apply the change. Without it the tool only previews." },
+            },
+            "required": ["task"],
+        });
+
+        let mut tools = vec![
+            json!({
+                "name": "list_tasks",
+                "description": "List Layer 4 tasks. Active tasks are numbered; done tasks are not.",
+                "inputSchema": { "type": "object", "properties": {} },
+            }),
+            json!({
+                "name": "get_task_prompt",
+                "description": "Get the full implementation prompt (blueprint, task and rules) for one task.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": { "task": { "type": "integer" } },
+                    "required": ["task"],
+                },
+            }),
+            json!({
+                "name": "validate",
+                "description": "Check blueprint completeness layer by layer.",
+                "inputSchema": { "type": "object", "properties": {} },
+            }),
+            json!({
+                "name": "start_task",
+                "description": "Move a task to IN PROGRESS. Pass confirm=true to apply.",
+                "inputSchema": task_num.clone(),
+            }),
+            json!({
+                "name": "complete_task",
+                "description": "Move a task to DONE. Pass confirm=true to apply.",
+                "inputSchema": task_num,
+            }),
+            json!({
+                "name": "propose_contract_change",
+                "description": "Record a proposed interface change under Layer 2 for the human to review. \
+                    Never edit contracts directly. Pass confirm=true to apply.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "contract": { "type": "string", "description": "Name of the interface to change" },
+                        "change": { "type": "string", "description": "What should change" },
+                        "reason": { "type": "string", "description": "Why the current contract is not enough" },
+                        "confirm": { "type": "boolean" },
+                    },
+                    "required": ["contract", "change", "reason"],
+                },
+            }),
+        ];
+
+        if self.read_only {
+            tools.retain(|t| !MUTATING_TOOLS.contains(&t["name"].as_str().unwrap_or("")));
+        }
+
+        json!({ "tools": tools })
+    }
+
+    fn call_tool(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(|n| n.as_str())
+            .ok_or((-32602, "Missing tool 'name'".to_string()))?;
+        let args = params.get("arguments").cloned().unwrap_or(json!({}));
+
+        if self.read_only && MUTATING_TOOLS.contains(&name) {
+            return Ok(tool_error(&format!(
+                "'{}' is disabled: the server was started with --read-only",
+                name
+            )));
+        }
+
+        let confirmed = args.get("confirm").and_then(|c| c.as_bool()).unwrap_or(false);
+        let task_arg = args.get("task").and_then(|t| t.as_u64()).map(|t| t as usize);
+
+        let bp = self.load()?;
+
+        let result = match name {
+            "list_tasks" => {
+                let active: Vec<Value> = bp
+                    .tasks
+                    .all_active_numbered()
+                    .into_iter()
+                    .map(|(num, task)| json!({ "number": num, "task": task }))
+                    .collect();
+                tool_text(&json!({ "active": active, "done": bp.tasks.done }).to_string())
+            }
+            "get_task_prompt" => match task_arg.and_then(|n| crate::prompt::generate_for_task(&bp, n, &self.config)) {
+                Some(prompt) => tool_text(&prompt.to_string()),
+                None => tool_error("Task not found. Call list_tasks to see task numbers."),
+            },
+            "validate" => {
+                let results = crate::validator::validate(&bp, &self.config);
+                tool_text(&serde_json::to_string(&results).unwrap_or_default())
+            }
+            "start_task" | "complete_task" => {
+                let target = if name == "start_task" {
+                    TaskStatus::InProgress
+                } else {
+                    TaskStatus::Done
+                };
+                self.move_task(&bp, task_arg, target, confirmed)
+            }
+            "propose_contract_change" => {
+                let field = |key: &str| args.get(key).and_then(|v| v.as_str()).unwrap_or("").trim();
+                let (contract, change, reason) = (field("contract"), field("change"), field("reason"));
+                if contract.is_empty() || change.is_empty() || reason.is_empty() {
+                    tool_error("'contract', 'change' and 'reason' are all required")
+                } else {
+                    let entry = format!("- [ ] **{}:** {} — _Reason:_ {}", contract, change, reason);
+                    if !confirmed {
+                        tool_text(&format!(
+                            "Preview - this line would be added under '### Proposed Changes' in Layer 2:\n{}\n\nCall again with confirm=true to record it.",
+                            entry
+                        ))
+                    } else {
+                        match record_proposal(&self.file, &entry) {
+                            Ok(_) => tool_text(&format!("Recorded proposal:\n{}\nWait for the human to accept it before changing the interface.", entry)),
+                            Err(e) => tool_error(&e.to_string()),
+                        }
+                    }
+                }
+            }
+            _ => return Err((-32602, format!("Unknown tool: {}", name))),
+        };
+
+        Ok(result)
+    }
+
+    fn move_task(
+        &self,
+        bp: &Blueprint,
+        task_num: Option<usize>,
+        target: TaskStatus,
+        confirmed: bool,
+    ) -> Value {
+        let Some(task_num) = task_num else {
+            return tool_error("Missing 'task' number");
+        };
+        let active = bp.tasks.all_active_numbered();
+        let Some((_, task)) = active.iter().find(|(n, _)| *n == task_num) else {
+            return tool_error(&format!("Task #{} not found", task_num));
+        };
+
+        if !confirmed {
+            return tool_text(&format!(
+                "Preview - task #{} '{}' would move to {}. Call again with confirm=true to apply.",
+                task_num,
+                task.text,
+                target.section_name()
+            ));
+        }
+
+        match crate::tasks::move_task(&self.file, task_num, target) {
+            Ok(task) => tool_text(&format!(
+                "Moved task #{} to {}: {}",
+                task_num,
+                target.section_name(),
+                task.text
+            )),
+            Err(e) => tool_error(&e.to_string()),
+        }
+    }
+}
+
+/// Append a proposal line to `### Proposed Changes` at the end of Layer 2, creating the heading if needed
+fn record_proposal(path: &Path, entry: &str) -> io::Result<()> {
+    let content = std::fs::read_to_string(path)?;
+
+    let (insert_at, prefix) = if let Some((_, end)) = find_section(&content, "### Proposed Changes") {
+        (end, "")
+    } else if let Some((_, end)) = find_section(&content, "Layer 2: Interface Contracts") {
+        (end, "### Proposed Changes\n\n")
+    } else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Could not find 'Layer 2: Interface Contracts' section in blueprint",
+        ));
+    };
+
+    // Keep a trailing `---` separator after the inserted text
+    let before = &content[..insert_at];
+    let (body, separator) = match before.trim_end().strip_suffix("---") {
+        Some(body) => (body.trim_end(), "\n---\n\n"),
+        None => (before.trim_end(), "\n\n"),
+    };
+
+    let joiner = if prefix.is_empty() { "\n" } else { "\n\n" };
+    let new_content = format!(
+        "{}{}{}{}\n{}{}",
+        body,
+        joiner,
+        prefix,
+        entry,
+        separator,
+        &content[insert_at..]
+    );
+
+    std::fs::write(path, new_content)
+}
+
+fn tool_text(text: &str) -> Value {
+    json!({ "content": [{ "type": "text", "text": text }] })
+}
+
+fn tool_error(text: &str) -> Value {
+    json!({ "content": [{ "type": "text", "text": text }], "isError": true })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
@@ -0,0 +1,336 @@
+use crate::blueprint::Heading;
+use crate::parser::outline;
+use crate::template::Schema;
+use regex::Regex;
+
+/// Sections that hold the same content under different names in different templates:
+/// (name, other name, field of the other section the content fills). Used in both
+/// directions when the target has no section with the same name.
+const ALIASES: [(&str, &str, Option<&str>); 4] = [
+    ("what are we building", "intent map", Some("ONE-LINE")),
+    ("files to create", "file skeleton", None),
+    ("file structure", "file skeleton", None),
+    ("tasks", "next up", None),
+];
+
+/// Where a piece of the old blueprint ended up
+#[derive(Debug, Clone)]
+pub struct Mapping {
+    pub from: String,
+    /// Heading (and field) in the new blueprint, `None` if it went to Unmapped
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub content: String,
+    pub mappings: Vec<Mapping>,
+    /// Sections the target template's schema requires but the result lacks
+    pub missing: Vec<&'static str>,
+}
+
+/// A destination in the target blueprint
+#[derive(Debug, Clone, PartialEq)]
+struct Slot {
+    /// Position of the target heading in the target's outline
+    heading: usize,
+    field: Option<&'static str>,
+}
+
+/// Move the sections of `source` into `target` (a rendered template): each section goes
+/// to the target section with the same name (or a known alias), sections with no
+/// match are searched for matching subsections, and whatever is left is collected in
+/// an `## Unmapped` section at the end
+pub fn migrate(source: &str, target: &str, schema: Schema) -> Migration {
+    let source_lines: Vec<&str> = source.lines().collect();
+    let target_headings = outline(target);
+    let mut filled: Vec<(Slot, Vec<String>)> = vec![];
+    let mut unmapped: Vec<(String, Vec<String>)> = vec![];
+    let mut mappings = vec![];
+
+    let headings = outline(source);
+    let mut skip_until = 0;
+    for (i, heading) in headings.iter().enumerate() {
+        if heading.line <= skip_until {
+            continue;
+        }
+        let children = &headings[i + 1..headings.partition_point(|h| h.line <= heading.end_line)];
+        // Only look inside a section if something in there has somewhere to go
+        let has_children = children
+            .iter()
+            .any(|child| find_slot(&child.title, &target_headings, target).is_some());
+
+        // Title headings span the whole document; only their children can be mapped
+        let slot = if heading.level == 1 { None } else { find_slot(&heading.title, &target_headings, target) };
+        match slot {
+            Some(slot) => {
+                let body = section_body(&source_lines, heading.line, heading.end_line);
+                mappings.push(Mapping {
+                    from: heading.title.clone(),
+                    to: Some(describe_slot(&slot, &target_headings)),
+                });
+                match filled.iter_mut().find(|(s, _)| *s == slot) {
+                    Some((_, existing)) => {
+                        existing.push(String::new());
+                        existing.extend(body);
+                    }
+                    None => filled.push((slot, body)),
+                }
+                skip_until = heading.end_line;
+            }
+            None => {
+                // Text before the first subsection (or the whole section) has no home
+                let end = if has_children { headings[i + 1].line - 1 } else { heading.end_line };
+                let mut body = section_body(&source_lines, heading.line, end);
+                if heading.level == 1 {
+                    body.retain(|line| !is_header_metadata(line));
+                } else {
+                    nest_headings(&mut body, heading.level);
+                }
+                if body.iter().any(|line| !line.trim().is_empty()) {
+                    let from = if heading.level == 1 { "Header".to_string() } else { heading.title.clone() };
+                    mappings.push(Mapping { from: from.clone(), to: None });
+                    unmapped.push((from, body));
+                }
+                if !has_children {
+                    skip_until = heading.end_line;
+                }
+            }
+        }
+    }
+
+    // Fill from the bottom up: headings before a filled section keep their position in
+    // the outline, though a parent's end moves when its subsections are filled
+    let mut lines: Vec<String> = target.lines().map(String::from).collect();
+    filled.sort_by_key(|(slot, _)| std::cmp::Reverse(slot.heading));
+    for (slot, body) in filled {
+        let current = outline(&lines.join("\n"));
+        let heading = &current[slot.heading];
+        match slot.field {
+            Some(field) => fill_field(&mut lines, heading, field, &body),
+            None => fill_section(&mut lines, heading, body),
+        }
+    }
+
+    if !unmapped.is_empty() {
+        while lines.last().is_some_and(|l| l.trim().is_empty()) {
+            lines.pop();
+        }
+        lines.push(String::new());
+        lines.push("---".to_string());
+        lines.push(String::new());
+        lines.push("## Unmapped".to_string());
+        lines.push(String::new());
+        lines.push("> Content from the old blueprint with no clear place in this template.".to_string());
+        lines.push("> Move it where it belongs, then delete this section.".to_string());
+        for (title, body) in unmapped {
+            lines.push(String::new());
+            lines.push(format!("### {}", title));
+            lines.push(String::new());
+            lines.extend(body);
+        }
+    }
+
+    let mut content = lines.join("\n");
+    content.push('\n');
+    let missing = schema
+        .required_sections()
+        .iter()
+        .copied()
+        .filter(|section| crate::parser::find_section(&content, section).is_none())
+        .collect();
+
+    Migration {
+        content,
+        mappings,
+        missing,
+    }
+}
+
+/// Heading title without numbering, layer prefixes, qualifiers or decoration, so
+/// "9. TASK QUEUE (By System)" and "Layer 4: Task Queue" compare equal
+fn normalize(title: &str) -> String {
+    let numbering = Regex::new(r"^(layer\s+\d+\s*:|\d+(\.\d+)*\.?)\s*").unwrap();
+    let qualifier = Regex::new(r"\([^)]*\)").unwrap();
+
+    let title = title.to_lowercase();
+    let title = numbering.replace(title.trim(), "");
+    let title = qualifier.replace_all(&title, "");
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn find_slot(title: &str, target_headings: &[Heading], target: &str) -> Option<Slot> {
+    let name = normalize(title);
+    let find = |wanted: &str| {
+        target_headings
+            .iter()
+            .position(|h| h.level > 1 && normalize(&h.title) == wanted)
+    };
+
+    if let Some(index) = find(&name) {
+        return Some(Slot {
+            heading: index,
+            field: None,
+        });
+    }
+
+    // The other way round, the whole section goes to the aliased one
+    if let Some(index) = ALIASES.iter().filter(|(_, other, _)| *other == name).find_map(|(alias, _, _)| find(alias)) {
+        return Some(Slot {
+            heading: index,
+            field: None,
+        });
+    }
+
+    if let Some((_, section, field)) = ALIASES.iter().find(|(alias, _, _)| *alias == name) {
+        if let Some(index) = find(section) {
+            // Only use the field if the target actually has it
+            let heading = &target_headings[index];
+            let field = field.filter(|f| {
+                let label = format!("**{}:**", f);
+                target
+                    .lines()
+                    .skip(heading.line)
+                    .take(heading.end_line - heading.line)
+                    .any(|l| l.trim_start().starts_with(&label))
+            });
+            return Some(Slot { heading: index, field });
+        }
+    }
+
+    None
+}
+
+fn describe_slot(slot: &Slot, target_headings: &[Heading]) -> String {
+    let title = target_headings[slot.heading].title.clone();
+    match slot.field {
+        Some(field) => format!("{} / {}", title, field),
+        None => title,
+    }
+}
+
+/// Lines after the heading at `line` up to `end_line`, without surrounding blank
+/// lines or a closing `---`
+fn section_body(lines: &[&str], line: usize, end_line: usize) -> Vec<String> {
+    let mut body: Vec<String> = lines[line.min(lines.len())..end_line.min(lines.len())].iter().map(|l| l.to_string()).collect();
+    while body.last().is_some_and(|l| l.trim().is_empty() || l.trim() == "---") {
+        body.pop();
+    }
+    while body.first().is_some_and(|l| l.trim().is_empty()) {
+        body.remove(0);
+    }
+    body
+}
+
+/// Shift the subheadings of a level-`level` section so they sit under its `###` heading
+/// in the Unmapped section
+fn nest_headings(body: &mut [String], level: usize) {
+    let mut in_fence = false;
+    for line in body.iter_mut() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        let depth = line.chars().take_while(|c| *c == '#').count();
+        if in_fence || depth == 0 || !line[depth..].starts_with(' ') {
+            continue;
+        }
+        let nested = (depth + 3).saturating_sub(level).min(6);
+        *line = format!("{}{}", "#".repeat(nested), &line[depth..]);
+    }
+}
+
+/// Template comments and `**Started:** 2024-01-01`-style lines the new template
+/// brings its own version of
+fn is_header_metadata(line: &str) -> bool {
+    let line = line.trim();
+    let dated = Regex::new(r"^>?\s*\*\*[^*]+:\*\*\s*\d{4}-\d{2}-\d{2}\s*$").unwrap();
+    (line.starts_with("<!--") && line.ends_with("-->")) || line == "---" || dated.is_match(line)
+}
+
+/// Replace a target section's body, keeping the `---` that separates it from the next one
+fn fill_section(lines: &mut Vec<String>, heading: &Heading, body: Vec<String>) {
+    let start = heading.line;
+    let end = heading.end_line.min(lines.len());
+    let closing = lines[start..end].iter().rev().find(|l| !l.trim().is_empty()).is_some_and(|l| l.trim() == "---");
+
+    let mut replacement = vec![String::new()];
+    replacement.extend(body);
+    replacement.push(String::new());
+    if closing {
+        replacement.push("---".to_string());
+        replacement.push(String::new());
+    }
+    lines.splice(start..end, replacement);
+}
+
+/// Put the text on a `**FIELD:**` line in a target section
+fn fill_field(lines: &mut [String], heading: &Heading, field: &str, body: &[String]) {
+    let label = format!("**{}:**", field);
+    let text = body.iter().map(|l| l.trim()).filter(|l| !l.is_empty()).collect::<Vec<_>>().join(" ");
+    let end = heading.end_line.min(lines.len());
+    if let Some(line) = lines[heading.line..end].iter_mut().find(|l| l.trim_start().starts_with(&label)) {
+        *line = format!("{} {}", label, text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+# Quick Start: Demo
+
+## What are we building?
+
+A tool.
+
+## Key decisions
+
+- Use Rust
+
+## Tasks
+
+- [ ] **Write the parser**
+";
+
+    const TARGET: &str = "\
+# Blueprint: Demo
+
+## Layer 1: Intent Map
+
+**ONE-LINE:** [what it does]
+
+## Layer 4: Task Queue
+
+### NEXT UP
+";
+
+    #[test]
+    fn collects_sections_without_a_home_in_unmapped() {
+        let migration = migrate(SOURCE, TARGET, Schema::Free);
+
+        let unmapped = crate::parser::section_text(&migration.content, "## Unmapped").unwrap();
+        assert!(unmapped.contains("### Key decisions\n\n- Use Rust"));
+        assert!(!unmapped.contains("A tool."));
+        assert!(!unmapped.contains("Write the parser"));
+
+        let to = |from: &str| migration.mappings.iter().find(|m| m.from == from).map(|m| m.to.clone());
+        assert_eq!(to("Key decisions"), Some(None));
+        assert_eq!(to("Tasks"), Some(Some("NEXT UP".to_string())));
+        assert!(migration.content.contains("**ONE-LINE:** A tool."));
+    }
+
+    #[test]
+    fn adds_no_unmapped_section_when_everything_fits() {
+        let source = SOURCE.replace("## Key decisions\n\n- Use Rust\n\n", "");
+        let migration = migrate(&source, TARGET, Schema::Free);
+        assert!(!migration.content.contains("## Unmapped"));
+        assert!(migration.mappings.iter().all(|m| m.to.is_some()));
+    }
+}
@@ -0,0 +1,362 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    Retain(usize),
+    Insert(String),
+    Delete(usize),
+}
+
+/// A plain-text edit that walks the whole document, in the ot.js wire format:
+/// `[5, "abc", -2, 10]` retains 5 characters, inserts "abc", deletes 2, retains 10.
+/// Lengths count `char`s; the browser matches this by working on `Array.from(text)`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextOperation {
+    pub ops: Vec<Op>,
+    /// Length of the document this operation applies to
+    pub base_len: usize,
+    /// Length of the document after applying it
+    pub target_len: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OtError {
+    /// The operation was built for a document of a different length
+    LengthMismatch { expected: usize, actual: usize },
+    /// Two operations being transformed do not share the same base document
+    Incompatible,
+}
+
+impl fmt::Display for OtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OtError::LengthMismatch { expected, actual } => write!(
+                f,
+                "operation expects a document of {} characters, got {}",
+                expected, actual
+            ),
+            OtError::Incompatible => write!(f, "operations do not share the same base document"),
+        }
+    }
+}
+
+impl TextOperation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn retain(&mut self, n: usize) -> &mut Self {
+        if n == 0 {
+            return self;
+        }
+        self.base_len += n;
+        self.target_len += n;
+        if let Some(Op::Retain(last)) = self.ops.last_mut() {
+            *last += n;
+        } else {
+            self.ops.push(Op::Retain(n));
+        }
+        self
+    }
+
+    pub fn insert(&mut self, s: &str) -> &mut Self {
+        if s.is_empty() {
+            return self;
+        }
+        self.target_len += s.chars().count();
+
+        // Keep inserts before deletes so equal operations have one canonical form
+        let len = self.ops.len();
+        match self.ops.as_mut_slice() {
+            [.., Op::Insert(last)] => last.push_str(s),
+            [.., Op::Insert(prev), Op::Delete(_)] => prev.push_str(s),
+            [.., Op::Delete(_)] => self.ops.insert(len - 1, Op::Insert(s.to_string())),
+            _ => self.ops.push(Op::Insert(s.to_string())),
+        }
+        self
+    }
+
+    pub fn delete(&mut self, n: usize) -> &mut Self {
+        if n == 0 {
+            return self;
+        }
+        self.base_len += n;
+        if let Some(Op::Delete(last)) = self.ops.last_mut() {
+            *last += n;
+        } else {
+            self.ops.push(Op::Delete(n));
+        }
+        self
+    }
+
+    /// True if applying this operation leaves the document unchanged
+    pub fn is_noop(&self) -> bool {
+        self.ops.iter().all(|op| matches!(op, Op::Retain(_)))
+    }
+
+    /// Build the operation that turns `old` into `new`: one replace between the
+    /// common prefix and common suffix
+    pub fn from_diff(old: &str, new: &str) -> Self {
+        let old: Vec<char> = old.chars().collect();
+        let new: Vec<char> = new.chars().collect();
+
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let max_suffix = old.len().min(new.len()) - prefix;
+        let suffix = old
+            .iter()
+            .rev()
+            .zip(new.iter().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let inserted: String = new[prefix..new.len() - suffix].iter().collect();
+
+        let mut op = TextOperation::new();
+        op.retain(prefix)
+            .insert(&inserted)
+            .delete(old.len() - suffix - prefix)
+            .retain(suffix);
+        op
+    }
+
+    pub fn apply(&self, doc: &str) -> Result<String, OtError> {
+        let chars: Vec<char> = doc.chars().collect();
+        if chars.len() != self.base_len {
+            return Err(OtError::LengthMismatch {
+                expected: self.base_len,
+                actual: chars.len(),
+            });
+        }
+
+        let mut result = String::with_capacity(doc.len());
+        let mut pos = 0;
+        for op in &self.ops {
+            match op {
+                Op::Retain(n) => {
+                    result.extend(&chars[pos..pos + n]);
+                    pos += n;
+                }
+                Op::Insert(s) => result.push_str(s),
+                Op::Delete(n) => pos += n,
+            }
+        }
+        Ok(result)
+    }
+
+    /// The operation that undoes this one; `doc` is the text this operation was applied to.
+    /// `doc` must be `base_len` characters long, so `apply` it first.
+    pub fn invert(&self, doc: &str) -> TextOperation {
+        let chars: Vec<char> = doc.chars().collect();
+        let mut inverse = TextOperation::new();
+        let mut pos = 0;
+        for op in &self.ops {
+            match op {
+                Op::Retain(n) => {
+                    inverse.retain(*n);
+                    pos += n;
+                }
+                Op::Insert(s) => {
+                    inverse.delete(s.chars().count());
+                }
+                Op::Delete(n) => {
+                    let deleted: String = chars[pos..pos + n].iter().collect();
+                    inverse.insert(&deleted);
+                    pos += n;
+                }
+            }
+        }
+        inverse
+    }
+
+    /// Transform two concurrent operations `a` and `b` (same base document) into
+    /// `(a', b')` such that `apply(apply(doc, a), b') == apply(apply(doc, b), a')`.
+    /// On a tie, `a`'s insert goes first; the server passes the incoming client
+    /// operation as `a` and its history as `b`, matching ot.js.
+    pub fn transform(a: &TextOperation, b: &TextOperation) -> Result<(TextOperation, TextOperation), OtError> {
+        if a.base_len != b.base_len {
+            return Err(OtError::Incompatible);
+        }
+
+        let mut a_prime = TextOperation::new();
+        let mut b_prime = TextOperation::new();
+
+        let mut a_ops = a.ops.iter().cloned();
+        let mut b_ops = b.ops.iter().cloned();
+        let mut op1 = a_ops.next();
+        let mut op2 = b_ops.next();
+
+        loop {
+            match (&op1, &op2) {
+                (None, None) => break,
+                (Some(Op::Insert(s)), _) => {
+                    a_prime.insert(s);
+                    b_prime.retain(s.chars().count());
+                    op1 = a_ops.next();
+                }
+                (_, Some(Op::Insert(s))) => {
+                    a_prime.retain(s.chars().count());
+                    b_prime.insert(s);
+                    op2 = b_ops.next();
+                }
+                (None, _) | (_, None) => return Err(OtError::Incompatible),
+                (Some(Op::Retain(n1)), Some(Op::Retain(n2))) => {
+                    let (n1, n2) = (*n1, *n2);
+                    let min = n1.min(n2);
+                    a_prime.retain(min);
+                    b_prime.retain(min);
+                    (op1, op2) = advance(n1, n2, Op::Retain, &mut a_ops, &mut b_ops);
+                }
+                (Some(Op::Delete(n1)), Some(Op::Delete(n2))) => {
+                    // Both deleted the same text; nothing left to do for this stretch
+                    let (n1, n2) = (*n1, *n2);
+                    (op1, op2) = advance(n1, n2, Op::Delete, &mut a_ops, &mut b_ops);
+                }
+                (Some(Op::Delete(n1)), Some(Op::Retain(n2))) => {
+                    let (n1, n2) = (*n1, *n2);
+                    a_prime.delete(n1.min(n2));
+                    (op1, op2) = advance_mixed(n1, n2, Op::Delete, Op::Retain, &mut a_ops, &mut b_ops);
+                }
+                (Some(Op::Retain(n1)), Some(Op::Delete(n2))) => {
+                    let (n1, n2) = (*n1, *n2);
+                    b_prime.delete(n1.min(n2));
+                    (op1, op2) = advance_mixed(n1, n2, Op::Retain, Op::Delete, &mut a_ops, &mut b_ops);
+                }
+            }
+        }
+
+        Ok((a_prime, b_prime))
+    }
+}
+
+/// Consume the shorter of two same-kind components and carry the remainder of the longer one
+fn advance<I: Iterator<Item = Op>>(
+    n1: usize,
+    n2: usize,
+    kind: fn(usize) -> Op,
+    a_ops: &mut I,
+    b_ops: &mut I,
+) -> (Option<Op>, Option<Op>) {
+    advance_mixed(n1, n2, kind, kind, a_ops, b_ops)
+}
+
+fn advance_mixed<I: Iterator<Item = Op>>(
+    n1: usize,
+    n2: usize,
+    kind1: fn(usize) -> Op,
+    kind2: fn(usize) -> Op,
+    a_ops: &mut I,
+    b_ops: &mut I,
+) -> (Option<Op>, Option<Op>) {
+    use std::cmp::Ordering;
+    match n1.cmp(&n2) {
+        Ordering::Greater => (Some(kind1(n1 - n2)), b_ops.next()),
+        Ordering::Equal => (a_ops.next(), b_ops.next()),
+        Ordering::Less => (a_ops.next(), Some(kind2(n2 - n1))),
+    }
+}
+
+/// Wire form of one component: number for retain/delete, string for insert
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Component {
+    Count(i64),
+    Text(String),
+}
+
+impl Serialize for TextOperation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let components: Vec<Component> = self
+            .ops
+            .iter()
+            .map(|op| match op {
+                Op::Retain(n) => Component::Count(*n as i64),
+                Op::Delete(n) => Component::Count(-(*n as i64)),
+                Op::Insert(s) => Component::Text(s.clone()),
+            })
+            .collect();
+        components.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TextOperation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let components = Vec::<Component>::deserialize(deserializer)?;
+        let mut op = TextOperation::new();
+        for component in components {
+            match component {
+                Component::Count(n) if n > 0 => op.retain(n as usize),
+                Component::Count(n) if n < 0 => op.delete(n.unsigned_abs() as usize),
+                Component::Count(_) => {
+                    return Err(serde::de::Error::custom("zero-length component"));
+                }
+                Component::Text(s) => op.insert(&s),
+            };
+        }
+        Ok(op)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(build: impl FnOnce(&mut TextOperation) -> &mut TextOperation) -> TextOperation {
+        let mut op = TextOperation::new();
+        build(&mut op);
+        op
+    }
+
+    fn assert_converges(doc: &str, a: &TextOperation, b: &TextOperation) -> String {
+        let (a_prime, b_prime) = TextOperation::transform(a, b).unwrap();
+        let left = b_prime.apply(&a.apply(doc).unwrap()).unwrap();
+        let right = a_prime.apply(&b.apply(doc).unwrap()).unwrap();
+        assert_eq!(left, right);
+        left
+    }
+
+    #[test]
+    fn transform_converges_on_edits_in_different_places() {
+        let doc = "hello world";
+        let a = op(|o| o.retain(5).insert(",").retain(6));
+        let b = op(|o| o.retain(6).delete(5).insert("there"));
+        assert_eq!(assert_converges(doc, &a, &b), "hello, there");
+    }
+
+    #[test]
+    fn transform_puts_a_first_on_a_tie() {
+        let doc = "ab";
+        let a = op(|o| o.retain(1).insert("X").retain(1));
+        let b = op(|o| o.retain(1).insert("Y").retain(1));
+        assert_eq!(assert_converges(doc, &a, &b), "aXYb");
+    }
+
+    #[test]
+    fn transform_converges_on_overlapping_deletes() {
+        let doc = "abcdef";
+        let a = op(|o| o.retain(1).delete(3).retain(2));
+        let b = op(|o| o.retain(2).delete(3).insert("é").retain(1));
+        assert_eq!(assert_converges(doc, &a, &b), "aéf");
+    }
+
+    #[test]
+    fn invert_undoes_the_operation() {
+        let doc = "héllo world";
+        let edit = op(|o| o.retain(1).delete(4).insert("ey").retain(6));
+        let edited = edit.apply(doc).unwrap();
+        assert_eq!(edited, "hey world");
+        assert_eq!(edit.invert(doc).apply(&edited).unwrap(), doc);
+    }
+
+    #[test]
+    fn rejects_operations_for_another_length() {
+        let edit = op(|o| o.delete(10));
+        assert_eq!(
+            edit.apply("abc"),
+            Err(OtError::LengthMismatch { expected: 10, actual: 3 })
+        );
+        let other = op(|o| o.retain(3));
+        assert_eq!(TextOperation::transform(&edit, &other), Err(OtError::Incompatible));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::blueprint::{Blueprint, TaskItem, TaskQueue, TaskStatus};
+use crate::blueprint::{Blueprint, Heading, TaskItem, TaskQueue, TaskStatus};
 use regex::Regex;
 use std::path::PathBuf;
 
@@ -36,6 +36,19 @@ fn extract_project_name(content: &str) -> Option<String> {
     None
 }
 
+/// Project name from the title: "# Blueprint: Name", or the text after the colon of the
+/// first top-level heading for templates with other titles ("# Quick Start: Name")
+pub fn title_project_name(content: &str) -> Option<String> {
+    extract_project_name(content).or_else(|| {
+        content
+            .lines()
+            .find(|line| line.starts_with("# "))
+            .and_then(|line| line.rsplit_once(':'))
+            .map(|(_, name)| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+    })
+}
+
 /// Check if a layer has real content (not just placeholders)
 fn check_layer(content: &str, layer_heading: &str) -> bool {
     if let Some((start, end)) = find_section(content, layer_heading) {
@@ -64,10 +77,9 @@ pub fn find_section(content: &str, heading: &str) -> Option<(usize, usize)> {
     let mut heading_level = 0;
 
     for (idx, line) in lines.iter().enumerate() {
-        let trimmed = line.trim();
-        if trimmed.to_lowercase().contains(&heading_lower) && trimmed.starts_with('#') {
-            // Count # symbols to determine level
-            heading_level = trimmed.chars().take_while(|c| *c == '#').count();
+        let Some(level) = heading_level_of(line) else { continue };
+        if line.trim().to_lowercase().contains(&heading_lower) {
+            heading_level = level;
             heading_line_idx = Some(idx);
             break;
         }
@@ -77,14 +89,10 @@ pub fn find_section(content: &str, heading: &str) -> Option<(usize, usize)> {
 
     // Find the end: next heading of same or higher level
     let mut end_line_idx = lines.len();
-    for idx in (heading_line_idx + 1)..lines.len() {
-        let trimmed = lines[idx].trim();
-        if trimmed.starts_with('#') {
-            let level = trimmed.chars().take_while(|c| *c == '#').count();
-            if level <= heading_level {
-                end_line_idx = idx;
-                break;
-            }
+    for (idx, line) in lines.iter().enumerate().skip(heading_line_idx + 1) {
+        if heading_level_of(line).is_some_and(|level| level <= heading_level) {
+            end_line_idx = idx;
+            break;
         }
     }
 
@@ -99,11 +107,90 @@ pub fn find_section(content: &str, heading: &str) -> Option<(usize, usize)> {
     Some((start_byte, end_byte))
 }
 
+/// Number of leading `#` if `line` is a heading, as `find_section` sees headings
+fn heading_level_of(line: &str) -> Option<usize> {
+    let trimmed = line.trim();
+    trimmed
+        .starts_with('#')
+        .then(|| trimmed.chars().take_while(|c| *c == '#').count())
+}
+
+/// Every heading with the lines its section spans, using the same rules as
+/// `find_section`: a section runs until the next heading of the same or higher level
+pub fn outline(content: &str) -> Vec<Heading> {
+    let lines: Vec<&str> = content.lines().collect();
+    let headings: Vec<(usize, usize)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, line)| heading_level_of(line).map(|level| (idx, level)))
+        .collect();
+
+    headings
+        .iter()
+        .enumerate()
+        .map(|(i, &(idx, level))| {
+            let end_line = headings[i + 1..]
+                .iter()
+                .find(|(_, next)| *next <= level)
+                .map_or(lines.len(), |(next_idx, _)| *next_idx);
+            Heading {
+                level,
+                title: lines[idx].trim().trim_start_matches('#').trim().to_string(),
+                line: idx + 1,
+                end_line,
+            }
+        })
+        .collect()
+}
+
+/// 1-based line number of the heading `find_section` would match
+pub fn heading_line(content: &str, heading: &str) -> Option<usize> {
+    let (start, _) = find_section(content, heading)?;
+    Some(content[..start].matches('\n').count().max(1))
+}
+
+/// 1-based line of a task in the file. Task line numbers count from the line after
+/// the heading of the lane the task was parsed from.
+pub fn task_line(content: &str, task: &TaskItem) -> Option<usize> {
+    let section = format!("### {}", task.status.section_name());
+    Some(heading_line(content, &section)? + task.line_number)
+}
+
+/// Trimmed body of a section by heading, excluding the heading line and any trailing `---` rule
+pub fn section_text<'a>(content: &'a str, heading: &str) -> Option<&'a str> {
+    let (start, end) = find_section(content, heading)?;
+    Some(content[start..end].trim().trim_end_matches("---").trim_end())
+}
+
+/// Lines of a section still holding a template placeholder like `- [actor 1]`, with
+/// their line numbers. Checkboxes and links don't count.
+pub fn placeholder_lines(content: &str, heading: &str) -> Vec<(usize, String)> {
+    let Some((start, end)) = find_section(content, heading) else {
+        return vec![];
+    };
+    let first_line = content[..start].matches('\n').count() + 1;
+    let placeholder = Regex::new(r"\[[^\[\]]{2,}\]").unwrap();
+
+    content[start..end]
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim();
+            !line.starts_with('>')
+                && !line.starts_with("<!--")
+                && placeholder
+                    .find_iter(line)
+                    .any(|m| !line[m.end()..].starts_with('('))
+        })
+        .map(|(idx, line)| (first_line + idx, line.trim().to_string()))
+        .collect()
+}
+
 /// Check if a section has real content (not just placeholders or empty)
 fn section_has_content(section: &str) -> bool {
     let placeholders = vec![
         "[name]",
-        "[what it does]",
+        "[what it does",
         "[actor",
         "[action]",
         "[hard part",
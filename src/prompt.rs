@@ -1,42 +1,129 @@
 use crate::blueprint::Blueprint;
+use crate::config::Loaded;
+use crate::parser::{section_text, task_line};
 
-pub fn generate(bp: &Blueprint) -> String {
-    let mut output = String::new();
+/// Rules for an AI agent working from the whole Blueprint. Rules refer to the context by
+/// its tags, since they may travel in a separate system message.
+pub const RULES: &str = "\
+1. Read the Blueprint in <blueprint> carefully before writing any code.
+2. Follow the interface contracts exactly — types, method signatures, rules.
+3. Place files according to the File Skeleton. Do not invent new directories.
+4. If you need a new type, tell me — I'll add it to Layer 2 first.
+5. If you need to change an interface, STOP and explain why before changing it.
+6. When you finish a task, tell me:
+   - What files you created/modified
+   - Any new types or interfaces you introduced
+   - What should be updated in the Blueprint
+7. Work on ONE task from the Task Queue at a time.
+";
 
-    // Header
-    output.push_str("You are implementing code for this project. Follow these rules:\n\n");
+/// A generated prompt, split so it can be printed as one blob or sent as chat messages
+pub struct Prompt {
+    /// One-line framing for the agent
+    pub header: String,
+    /// Blueprint, task and other project material, already wrapped in XML-ish tags
+    pub context: String,
+    /// Numbered rules, without the surrounding `<rules>` tags
+    pub rules: String,
+}
+
+/// Output format for `nira prompt`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PromptFormat {
+    /// One text blob for copy-paste
+    Text,
+    /// JSON chat messages for model APIs
+    Messages,
+}
+
+/// Provider message schema for `--format messages`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MessageSchema {
+    /// `[{"role": "system", ...}, {"role": "user", ...}]` (OpenAI-compatible chat completions)
+    Openai,
+    /// `{"system": ..., "messages": [...]}` (Anthropic Messages API)
+    Anthropic,
+    /// `{"systemInstruction": ..., "contents": [...]}` (Gemini generateContent)
+    Gemini,
+}
+
+impl Prompt {
+    /// System message: the framing and the rules
+    pub fn system(&self) -> String {
+        format!("{}\n\n<rules>\n{}</rules>\n", self.header, self.rules)
+    }
+
+    /// Chat messages in the given provider schema, with an optional assistant prefill
+    pub fn to_messages(&self, schema: MessageSchema, prefill: Option<&str>) -> serde_json::Value {
+        use serde_json::json;
+
+        let system = self.system();
+        let user = self.context.trim_end();
+
+        match schema {
+            MessageSchema::Openai => {
+                let mut messages = vec![
+                    json!({ "role": "system", "content": system }),
+                    json!({ "role": "user", "content": user }),
+                ];
+                if let Some(prefill) = prefill {
+                    messages.push(json!({ "role": "assistant", "content": prefill }));
+                }
+                json!(messages)
+            }
+            MessageSchema::Anthropic => {
+                let mut messages = vec![json!({ "role": "user", "content": user })];
+                if let Some(prefill) = prefill {
+                    messages.push(json!({ "role": "assistant", "content": prefill }));
+                }
+                json!({ "system": system, "messages": messages })
+            }
+            MessageSchema::Gemini => {
+                let mut contents = vec![json!({ "role": "user", "parts": [{ "text": user }] })];
+                if let Some(prefill) = prefill {
+                    contents.push(json!({ "role": "model", "parts": [{ "text": prefill }] }));
+                }
+                json!({
+                    "systemInstruction": { "parts": [{ "text": system }] },
+                    "contents": contents,
+                })
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Prompt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\n\n{}<rules>\n{}</rules>\n", self.header, self.context, self.rules)
+    }
+}
+
+pub fn generate(bp: &Blueprint, config: &Loaded) -> Prompt {
+    let mut output = String::new();
 
     // The blueprint itself, wrapped in XML tags
     output.push_str("<blueprint>\n");
     output.push_str(&bp.raw);
     output.push_str("\n</blueprint>\n\n");
 
-    // AI agent rules
-    output.push_str("<rules>\n");
-    output.push_str("1. Read the Blueprint above carefully before writing any code.\n");
-    output.push_str("2. Follow the interface contracts exactly — types, method signatures, rules.\n");
-    output.push_str("3. Place files according to the File Skeleton. Do not invent new directories.\n");
-    output.push_str("4. If you need a new type, tell me — I'll add it to Layer 2 first.\n");
-    output.push_str("5. If you need to change an interface, STOP and explain why before changing it.\n");
-    output.push_str("6. When you finish a task, tell me:\n");
-    output.push_str("   - What files you created/modified\n");
-    output.push_str("   - Any new types or interfaces you introduced\n");
-    output.push_str("   - What should be updated in the Blueprint\n");
-    output.push_str("7. Work on ONE task from the Task Queue at a time.\n");
-    output.push_str("</rules>\n");
-
-    output
+    Prompt {
+        header: "You are implementing code for this project. Follow these rules:".to_string(),
+        context: output,
+        rules: full_rules(config),
+    }
 }
 
-pub fn generate_for_task(bp: &Blueprint, task_num: usize) -> Option<String> {
+/// Rules for prompts on the whole Blueprint: the `full` prompt file from nira.toml, or `RULES`
+pub fn full_rules(config: &Loaded) -> String {
+    config.prompt(&config.config.prompts.full).unwrap_or_else(|| RULES.to_string())
+}
+
+pub fn generate_for_task(bp: &Blueprint, task_num: usize, config: &Loaded) -> Option<Prompt> {
     let active_tasks = bp.tasks.all_active_numbered();
     let (_, task) = active_tasks.iter().find(|(num, _)| *num == task_num)?;
 
     let mut output = String::new();
 
-    // Header
-    output.push_str("You are implementing a specific task for this project.\n\n");
-
     // The blueprint
     output.push_str("<blueprint>\n");
     output.push_str(&bp.raw);
@@ -60,18 +147,123 @@ pub fn generate_for_task(bp: &Blueprint, task_num: usize) -> Option<String> {
 
     output.push_str("</current_task>\n\n");
 
-    // AI agent rules (focused on this task)
-    output.push_str("<rules>\n");
-    output.push_str("1. Read the Blueprint and understand the full context.\n");
-    output.push_str("2. Focus ONLY on the current task specified above.\n");
-    output.push_str("3. Follow the interface contracts from Layer 2 exactly.\n");
-    output.push_str("4. Place files according to Layer 3 (File Skeleton).\n");
-    output.push_str("5. If you need to change an interface, STOP and explain why.\n");
-    output.push_str("6. When done, report:\n");
-    output.push_str("   - What files you created/modified\n");
-    output.push_str("   - Any new types or interfaces\n");
-    output.push_str("   - What to update in the Blueprint\n");
-    output.push_str("</rules>\n");
-
-    Some(output)
+    // AI agent rules (focused on this task), unless nira.toml has its own
+    let rules = config.prompt(&config.config.prompts.task).unwrap_or_else(|| {
+        let mut rules = String::new();
+        rules.push_str("1. Read the Blueprint in <blueprint> and understand the full context.\n");
+        rules.push_str("2. Focus ONLY on the task in <current_task>.\n");
+        rules.push_str("3. Follow the interface contracts from Layer 2 exactly.\n");
+        rules.push_str("4. Place files according to Layer 3 (File Skeleton).\n");
+        rules.push_str("5. If you need to change an interface, STOP and explain why.\n");
+        rules.push_str("6. When done, report:\n");
+        rules.push_str("   - What files you created/modified\n");
+        rules.push_str("   - Any new types or interfaces\n");
+        rules.push_str("   - What to update in the Blueprint\n");
+        rules
+    });
+
+    Some(Prompt {
+        header: "You are implementing a specific task for this project.".to_string(),
+        context: output,
+        rules,
+    })
+}
+
+/// Recovery prompt for re-grounding a fresh agent session after the previous one went off-track.
+/// Follows the Recovery Checklist: intent, contracts, skeleton, then the task that was in flight.
+/// Returns None when nothing is IN PROGRESS.
+pub fn generate_recovery(bp: &Blueprint, config: &Loaded) -> Option<Prompt> {
+    let task = bp.tasks.in_progress.first()?;
+
+    let mut output = String::new();
+
+    // Header
+    let header = match &bp.project_name {
+        Some(name) => format!(
+            "You are resuming work on {} after a previous session went off-track.",
+            name
+        ),
+        None => "You are resuming work on this project after a previous session went off-track."
+            .to_string(),
+    };
+
+    // Intent, contracts and skeleton are the source of truth for what was planned
+    output.push_str("<intent>\n");
+    output.push_str(section_or_placeholder(bp, "Layer 1: Intent Map"));
+    output.push_str("\n</intent>\n\n");
+
+    output.push_str("<contracts>\n");
+    output.push_str(section_or_placeholder(bp, "Layer 2: Interface Contracts"));
+    output.push_str("\n</contracts>\n\n");
+
+    output.push_str("<skeleton>\n");
+    output.push_str(section_or_placeholder(bp, "Layer 3: File Skeleton"));
+    output.push_str("\n</skeleton>\n\n");
+
+    // The task that was in flight
+    output.push_str("<task_in_progress>\n");
+    output.push_str(&format!("{}\n\n", task.text));
+
+    if let Some(context) = &task.context {
+        output.push_str(&format!("Context: {}\n\n", context));
+    }
+
+    if let Some(files) = &task.files {
+        output.push_str(&format!("Files: {}\n\n", files));
+    }
+
+    output.push_str("</task_in_progress>\n\n");
+
+    // What actually changed on disk since the task was started, minus ignored paths
+    output.push_str("<changed_files>\n");
+    let changes = task_line(&bp.raw, task)
+        .and_then(|line| crate::git::changed_since_line(&bp.path, line))
+        .map(|mut changes| {
+            changes.files.retain(|file| !config.is_ignored(&changes.root.join(file)));
+            changes
+        });
+    match changes {
+        Some(changes) if changes.files.is_empty() => {
+            output.push_str(&format!("No files changed since {}.\n", changes.since));
+        }
+        Some(changes) => {
+            output.push_str(&format!("Changed since {} (paths from the repository root):\n", changes.since));
+            for file in &changes.files {
+                output.push_str(&format!("- {}\n", file));
+            }
+        }
+        None => output.push_str("Git history unavailable - inspect the working tree yourself.\n"),
+    }
+    output.push_str("</changed_files>\n\n");
+
+    // Recovery rules, unless nira.toml has its own
+    let rules = config.prompt(&config.config.prompts.recover).unwrap_or_else(|| {
+        let mut rules = String::new();
+        rules.push_str("1. Do NOT write new code yet.\n");
+        rules.push_str("2. Read <intent>, <contracts> and <skeleton>, then read each file in <changed_files>.\n");
+        rules.push_str("3. Reconcile what exists with what was planned. Report:\n");
+        rules.push_str("   - Which parts of the task are done and match the contracts\n");
+        rules.push_str("   - Any types, interfaces or files that were added without being in the Blueprint\n");
+        rules.push_str("   - Any code that contradicts a contract or sits outside the File Skeleton\n");
+        rules.push_str("   - What remains to finish the task\n");
+        rules.push_str("4. Propose Blueprint updates or reverts for every mismatch and wait for my decision.\n");
+        rules.push_str("5. Once we agree, continue the task and follow the contracts exactly.\n");
+        rules
+    });
+
+    Some(Prompt {
+        header,
+        context: output,
+        rules,
+    })
+}
+
+/// Rough token estimate (about 4 characters per token for English text and code)
+pub fn approx_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Content of a layer section, or a note that it is missing
+fn section_or_placeholder<'a>(bp: &'a Blueprint, heading: &str) -> &'a str {
+    section_text(&bp.raw, heading).unwrap_or("(not defined in the Blueprint)")
 }
@@ -0,0 +1,40 @@
+use pulldown_cmark::{html, Event, Options, Parser};
+
+/// Render blueprint markdown to HTML for the editor preview (GitHub-flavoured:
+/// tables, task list checkboxes, strikethrough, fenced code)
+pub fn to_html(markdown: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_FOOTNOTES);
+
+    // Single newlines break the line, as GitHub comments and the old preview did.
+    // Raw HTML is shown as text: the preview goes into innerHTML next to the
+    // session token, and blueprints can come from agents over MCP
+    let events = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::SoftBreak => Event::HardBreak,
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        event => event,
+    });
+
+    let mut out = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut out, events);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_html_is_escaped() {
+        let html = to_html("<script>alert(1)</script>\n\nText <img src=x onerror=alert(1)> here");
+        assert!(!html.contains("<script"), "{html}");
+        assert!(!html.contains("<img"), "{html}");
+        assert!(html.contains("&lt;script&gt;"), "{html}");
+        // onerror only survives as escaped text, never as an attribute
+        assert_eq!(html.matches("onerror").count(), 1, "{html}");
+        assert!(html.contains("&lt;img src=x onerror=alert(1)&gt;"), "{html}");
+    }
+}
@@ -1,81 +1,293 @@
+use crate::blueprint::{Diagnostic, Heading, TaskItem, TaskStatus};
+use crate::config::Loaded;
+use crate::document::{Applied, Document};
+use crate::ot::TextOperation;
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        FromRequestParts, Path, Query, RawPathParams, Request, State,
     },
-    http::StatusCode,
+    http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode},
+    middleware::{self, Next},
     response::{Html, IntoResponse, Response},
-    routing::get,
+    routing::{get, patch, post},
     Router,
 };
 use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use serde::{Deserialize, Serialize};
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, Notify, RwLock};
 
 const HTML: &str = include_str!("../editor/index.html");
 
+/// How long to wait after the last edit before writing the file
+const SAVE_DELAY: Duration = Duration::from_millis(300);
+
+/// Quiet period after the last file event before reading the file, so a
+/// write (or truncate + write, or temp file + rename) is seen only once finished
+const WRITE_SETTLE: Duration = Duration::from_millis(100);
+
+/// Longest a steady stream of file events can postpone reading the file
+const MAX_SETTLE: Duration = Duration::from_secs(2);
+
+/// How long content has to stay unchanged before diagnostics are recomputed
+const LINT_DELAY: Duration = Duration::from_millis(150);
+
+/// Request header naming the origin of a REST write, echoed in the broadcast edit
+const ORIGIN_HEADER: &str = "x-nira-origin";
+
 #[derive(Clone)]
 struct AppState {
+    /// Every blueprint being served; the first is the default for unscoped routes
+    docs: Arc<Vec<Arc<ServedDoc>>>,
+    /// Ids of documents whose content changed, for views spanning the workspace
+    changes_tx: broadcast::Sender<String>,
+    next_client: Arc<AtomicU64>,
+    access: Arc<Access>,
+}
+
+/// One blueprint file with its live document, its own broadcast channel and its own watcher
+struct ServedDoc {
+    id: String,
     file_path: PathBuf,
-    content: Arc<RwLock<String>>,
-    broadcast_tx: broadcast::Sender<String>,
+    /// Path shown in the sidebar, relative to the workspace root
+    display_path: String,
+    doc: RwLock<Document>,
+    broadcast_tx: broadcast::Sender<ServerMessage>,
+    save_notify: Notify,
+    /// Snapshots to keep in `.nira/history/` (0 disables history)
+    history_keep: usize,
+    /// Project settings the diagnostics are computed with
+    config: Arc<Loaded>,
 }
 
-pub async fn serve(file: PathBuf, port: u16) {
-    // Read initial content
-    let content = match std::fs::read_to_string(&file) {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("Error: Failed to read {}: {}", file.display(), e);
-            std::process::exit(1);
+impl ServedDoc {
+    /// Record that the document's content is now on disk, and snapshot it
+    fn mark_saved(&self, doc: &mut Document) {
+        doc.mark_saved();
+        self.snapshot(&doc.content);
+    }
+
+    fn snapshot(&self, content: &str) {
+        if let Err(e) = crate::history::record(&self.file_path, content, self.history_keep) {
+            eprintln!("Warning: Could not record history for {}: {}", self.file_path.display(), e);
         }
-    };
+    }
+}
+
+/// The document a request is about: `/api/docs/{doc}/...`, or the default one for `/api/...`
+struct Doc(Arc<ServedDoc>);
+
+impl FromRequestParts<AppState> for Doc {
+    type Rejection = Response;
 
-    // Create broadcast channel for WebSocket updates
-    let (broadcast_tx, _) = broadcast::channel::<String>(100);
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let params = RawPathParams::from_request_parts(parts, state).await.ok();
+        let id = params
+            .as_ref()
+            .and_then(|params| params.iter().find(|(key, _)| *key == "doc").map(|(_, value)| value));
+
+        match id {
+            None => Ok(Doc(state.docs[0].clone())),
+            Some(id) => state
+                .docs
+                .iter()
+                .find(|served| served.id == id)
+                .map(|served| Doc(served.clone()))
+                .ok_or_else(|| (StatusCode::NOT_FOUND, format!("No blueprint '{}' is being served\n", id)).into_response()),
+        }
+    }
+}
+
+/// How `nira serve` listens and who may talk to it
+pub struct ServeOptions {
+    pub host: String,
+    pub port: u16,
+    /// Open the editor in the default browser once listening
+    pub open: bool,
+    /// Snapshots kept per blueprint in `.nira/history/`
+    pub history_keep: usize,
+    pub config: Loaded,
+}
+
+/// Per-session access control: the token from the opened URL, and the host the
+/// server was bound to (accepted in Host headers besides localhost and IP literals)
+struct Access {
+    token: String,
+    host: String,
+}
+
+/// Messages pushed to browser tabs over the WebSocket
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    /// Full document, sent when the client has no usable revision to catch up from
+    Init { revision: u64, content: String },
+    /// A committed edit. `origin` is the tab id that made it, "disk" for external
+    /// file changes or the REST caller's origin; the tab whose `origin` and `seq`
+    /// match treats it as its acknowledgement instead of applying it again.
+    Edit {
+        revision: u64,
+        op: TextOperation,
+        origin: String,
+        seq: u64,
+    },
+    /// The client's edit was already committed earlier (a resend after reconnect)
+    Ack { revision: u64, seq: u64 },
+    /// Catch-up replay is done; the client can (re)send pending edits. `epoch`
+    /// prefixes revisions in ETags
+    Ready { revision: u64, epoch: &'static str },
+    /// The client's edit could not be merged; it has to reconcile against `content`
+    Conflict {
+        revision: u64,
+        content: String,
+        message: String,
+    },
+    /// Validation findings for the document at `revision`
+    Diagnostics {
+        revision: u64,
+        diagnostics: Vec<Diagnostic>,
+    },
+    /// Another blueprint in the workspace changed (for the combined task board)
+    DocChanged { doc: String },
+}
+
+/// Messages received from browser tabs
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    /// An edit made against `revision`
+    Edit {
+        revision: u64,
+        op: TextOperation,
+        seq: u64,
+    },
+}
 
+#[derive(Debug, Deserialize)]
+struct ConnectParams {
+    /// Stable id for the tab, so resent edits after a reconnect are recognised
+    client: Option<String>,
+    /// Last revision the tab has; committed edits after it are replayed
+    since: Option<u64>,
+}
+
+/// Serve the given blueprints; ids and sidebar paths are relative to `root`
+pub async fn serve(root: &FsPath, files: Vec<PathBuf>, options: ServeOptions) {
+    let config = Arc::new(options.config.clone());
+    let mut docs = Vec::new();
+    for file in files {
+        // Read initial content
+        let content = match std::fs::read_to_string(&file) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("Error: Failed to read {}: {}", file.display(), e);
+                std::process::exit(1);
+            }
+        };
+
+        // Ids come from paths, so two paths can only collide after slugging; number the repeats
+        let base_id = crate::workspace::doc_id(root, &file);
+        let mut id = base_id.clone();
+        let mut n = 2;
+        while docs.iter().any(|d: &Arc<ServedDoc>| d.id == id) {
+            id = format!("{}-{}", base_id, n);
+            n += 1;
+        }
+
+        // Create broadcast channel for WebSocket updates
+        let (broadcast_tx, _) = broadcast::channel::<ServerMessage>(100);
+
+        docs.push(Arc::new(ServedDoc {
+            id,
+            display_path: file.strip_prefix(root).unwrap_or(&file).display().to_string(),
+            file_path: file,
+            doc: RwLock::new(Document::new(content)),
+            broadcast_tx,
+            save_notify: Notify::new(),
+            history_keep: options.history_keep,
+            config: config.clone(),
+        }));
+    }
+
+    let (changes_tx, _) = broadcast::channel::<String>(100);
     let state = AppState {
-        file_path: file.clone(),
-        content: Arc::new(RwLock::new(content)),
-        broadcast_tx: broadcast_tx.clone(),
+        docs: Arc::new(docs),
+        changes_tx: changes_tx.clone(),
+        next_client: Arc::new(AtomicU64::new(1)),
+        access: Arc::new(Access {
+            token: new_token(),
+            host: options.host.clone(),
+        }),
     };
 
-    // Start file watcher
-    let watch_state = state.clone();
-    let watch_file = file.clone();
-    tokio::spawn(async move {
-        if let Err(e) = watch_file_changes(watch_file, watch_state).await {
-            eprintln!("File watcher error: {}", e);
-        }
-    });
+    for served in state.docs.iter() {
+        // The version we started from is the first thing a bad edit could destroy
+        served.snapshot(&served.doc.read().await.content);
+
+        // Start file watcher
+        let watched = served.clone();
+        tokio::spawn(async move {
+            let file = watched.file_path.clone();
+            if let Err(e) = watch_file_changes(watched).await {
+                eprintln!("File watcher error for {}: {}", file.display(), e);
+            }
+        });
+
+        // Write committed edits to disk shortly after they stop coming in
+        tokio::spawn(save_loop(served.clone()));
+
+        // Re-validate after edits and push the results to every tab
+        tokio::spawn(lint_loop(served.clone(), changes_tx.clone()));
+    }
 
-    // Build router
+    // Build router: document routes answer for the default blueprint under /api
+    // and for any blueprint under /api/docs/{doc}
     let app = Router::new()
         .route("/", get(serve_index))
-        .route("/api/blueprint", get(get_blueprint).put(put_blueprint))
-        .route("/api/validate", get(get_validation))
+        .route("/api/render", post(render_preview))
+        .route("/api/docs", get(list_docs))
+        .route("/api/workspace/tasks", get(get_workspace_tasks))
         .route("/ws", get(websocket_handler))
-        .with_state(state);
+        .nest("/api", doc_routes())
+        .nest("/api/docs/{doc}", doc_routes())
+        .layer(middleware::from_fn_with_state(state.clone(), guard))
+        .with_state(state.clone());
+
+    if state.docs.len() > 1 {
+        println!("✓ Serving {} blueprints from {}", state.docs.len(), root.display());
+    }
 
     // Bind server
-    let addr = format!("127.0.0.1:{}", port);
-    let listener = match tokio::net::TcpListener::bind(&addr).await {
+    let listener = match tokio::net::TcpListener::bind((options.host.as_str(), options.port)).await {
         Ok(l) => l,
         Err(e) => {
-            eprintln!("Error: Failed to bind to {}: {}", addr, e);
+            eprintln!("Error: Failed to bind to {}:{}: {}", options.host, options.port, e);
             std::process::exit(1);
         }
     };
 
-    println!("✓ Server started at http://{}", addr);
-    println!("\nOpening browser...");
+    // Listening on every interface: point the browser at this machine
+    let url_host = match options.host.parse::<std::net::IpAddr>() {
+        Ok(ip) if ip.is_unspecified() => "127.0.0.1".to_string(),
+        Ok(std::net::IpAddr::V6(ip)) => format!("[{}]", ip),
+        _ => options.host.clone(),
+    };
+    let url = format!("http://{}:{}/?token={}", url_host, options.port, state.access.token);
+
+    println!("✓ Server started at http://{}:{}", url_host, options.port);
+    println!("  Editor URL (includes this session's access token):\n  {}", url);
 
     // Open browser
-    let url = format!("http://{}", addr);
-    if let Err(e) = open::that(&url) {
-        eprintln!("Warning: Failed to open browser: {}", e);
-        println!("Please open manually: {}", url);
+    if options.open {
+        println!("\nOpening browser...");
+        if let Err(e) = open::that(&url) {
+            eprintln!("Warning: Failed to open browser: {}", e);
+            println!("Please open the URL above manually.");
+        }
     }
 
     // Run server
@@ -85,71 +297,668 @@ pub async fn serve(file: PathBuf, port: u16) {
     }
 }
 
+/// A random 128-bit token, hex encoded
+fn new_token() -> String {
+    let mut bytes = [0u8; 16];
+    if let Err(e) = getrandom::fill(&mut bytes) {
+        eprintln!("Error: Could not generate an access token: {}", e);
+        std::process::exit(1);
+    }
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Reject requests whose Host could be a rebound DNS name, cross-origin requests,
+/// and API or WebSocket calls without the session token
+async fn guard(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let headers = request.headers();
+
+    if !host_allowed(headers, &state.access.host) {
+        return (StatusCode::FORBIDDEN, "Unrecognised Host header\n").into_response();
+    }
+    if !same_origin(headers) {
+        return (StatusCode::FORBIDDEN, "Cross-origin request rejected\n").into_response();
+    }
+
+    let path = request.uri().path();
+    if (path.starts_with("/api/") || path == "/ws") && !has_token(&request, &state.access.token) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            "Missing or wrong access token; open the URL printed by nira serve\n",
+        )
+            .into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Host must be localhost, an IP literal or the name the server was bound to.
+/// Anything else is a DNS name that may point at us without the user meaning it.
+fn host_allowed(headers: &HeaderMap, bound_host: &str) -> bool {
+    let Some(host) = headers.get(header::HOST).and_then(|h| h.to_str().ok()) else {
+        return false;
+    };
+    let name = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or(""),
+        None => host.split(':').next().unwrap_or(""),
+    };
+
+    name.eq_ignore_ascii_case("localhost")
+        || name.eq_ignore_ascii_case(bound_host)
+        || name.parse::<std::net::IpAddr>().is_ok()
+}
+
+/// A browser-sent Origin (present on writes and WebSocket upgrades) must match the Host
+fn same_origin(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return true;
+    };
+    let host = headers.get(header::HOST).and_then(|h| h.to_str().ok());
+    let authority = origin
+        .to_str()
+        .ok()
+        .and_then(|o| o.strip_prefix("http://").or_else(|| o.strip_prefix("https://")));
+
+    matches!((authority, host), (Some(a), Some(h)) if a.eq_ignore_ascii_case(h))
+}
+
+/// The token comes as `Authorization: Bearer <token>`, or as `?token=` where headers
+/// can't be set (WebSocket upgrades)
+fn has_token(request: &Request, token: &str) -> bool {
+    let from_header = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let from_query = request
+        .uri()
+        .query()
+        .and_then(|q| q.split('&').find_map(|pair| pair.strip_prefix("token=")));
+
+    from_header
+        .or(from_query)
+        .is_some_and(|given| constant_time_eq(given.trim().as_bytes(), token.as_bytes()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 async fn serve_index() -> Html<&'static str> {
     Html(HTML)
 }
 
-async fn get_blueprint(State(state): State<AppState>) -> impl IntoResponse {
-    let content = state.content.read().await;
-    content.clone()
+/// Routes about one document; the `Doc` extractor picks which
+fn doc_routes() -> Router<AppState> {
+    Router::new()
+        .route("/blueprint", get(get_blueprint).put(put_blueprint))
+        .route("/blueprint/merge", post(merge_blueprint))
+        .route("/validate", get(get_validation))
+        .route("/outline", get(get_outline))
+        .route("/tasks", get(get_tasks).post(post_task))
+        .route("/tasks/{n}/move", post(move_task))
+        .route("/tasks/{n}", patch(patch_task))
+        .route("/history", get(get_history))
+        .route("/history/{rev}", get(get_snapshot))
+        .route("/history/{rev}/restore", post(restore_snapshot))
+        .route("/ws", get(websocket_handler))
+}
+
+#[derive(Serialize)]
+struct DocInfo {
+    id: String,
+    path: String,
+    name: String,
+    default: bool,
+}
+
+async fn list_docs(State(state): State<AppState>) -> impl IntoResponse {
+    let mut docs = Vec::new();
+    for (i, served) in state.docs.iter().enumerate() {
+        let doc = served.doc.read().await;
+        let name = crate::parser::parse(&doc.content, served.file_path.clone())
+            .project_name
+            .unwrap_or_else(|| served.display_path.clone());
+        docs.push(DocInfo {
+            id: served.id.clone(),
+            path: served.display_path.clone(),
+            name,
+            default: i == 0,
+        });
+    }
+    axum::Json(docs)
+}
+
+async fn get_blueprint(Doc(served): Doc) -> impl IntoResponse {
+    let doc = served.doc.read().await;
+    ([(header::ETAG, etag(doc.revision))], doc.content.clone())
 }
 
 async fn put_blueprint(
-    State(state): State<AppState>,
+    Doc(served): Doc,
+    headers: HeaderMap,
     body: String,
-) -> Result<impl IntoResponse, StatusCode> {
+) -> Response {
+    let Some(if_match) = headers.get(header::IF_MATCH) else {
+        return (
+            StatusCode::PRECONDITION_REQUIRED,
+            "PUT /api/blueprint needs an If-Match header with the ETag from GET /api/blueprint\n",
+        )
+            .into_response();
+    };
+    let Some(expected) = parse_if_match(if_match) else {
+        return (StatusCode::PRECONDITION_FAILED, "Malformed If-Match header\n").into_response();
+    };
+
+    let mut doc = served.doc.write().await;
+
+    // Stale write: hand back the current text and a three-way diff instead of overwriting
+    let base = match expected {
+        IfMatch::Any => None,
+        IfMatch::Revision(revision) if revision == doc.revision => None,
+        IfMatch::Revision(revision) => Some(Some(revision)),
+        IfMatch::OtherProcess => Some(None),
+    };
+    if let Some(base) = base {
+        let conflict = merge_against(&doc, base, &body);
+        return (
+            StatusCode::CONFLICT,
+            [(header::ETAG, etag(doc.revision))],
+            axum::Json(conflict),
+        )
+            .into_response();
+    }
+
+    // A whole-document PUT is just another edit, so open tabs receive it as a diff.
+    // Callers that also hold a socket can name themselves to recognise the echo.
+    let origin = headers
+        .get(ORIGIN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("rest");
+    if let Some((revision, op)) = doc.replace(&body, origin) {
+        let _ = served.broadcast_tx.send(ServerMessage::Edit {
+            revision,
+            op,
+            origin: origin.to_string(),
+            seq: 0,
+        });
+    }
+
     // Write to file
-    if let Err(e) = std::fs::write(&state.file_path, &body) {
-        eprintln!("Error writing file: {}", e);
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    if doc.is_dirty() {
+        if let Err(e) = std::fs::write(&served.file_path, &doc.content) {
+            eprintln!("Error writing file: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+        served.mark_saved(&mut doc);
     }
 
-    // Update in-memory content
-    let mut content = state.content.write().await;
-    *content = body.clone();
+    (StatusCode::OK, [(header::ETAG, etag(doc.revision))]).into_response()
+}
+
+/// Dry run of a conditional PUT: the three-way merge of the body against the
+/// revision in If-Match, without writing anything
+async fn merge_blueprint(
+    Doc(served): Doc,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let base = match headers.get(header::IF_MATCH).and_then(parse_if_match) {
+        Some(IfMatch::Revision(revision)) => Some(revision),
+        Some(IfMatch::OtherProcess) => None,
+        Some(IfMatch::Any) | None => {
+            return (StatusCode::PRECONDITION_REQUIRED, "If-Match with a revision ETag is required\n")
+                .into_response();
+        }
+    };
+
+    let doc = served.doc.read().await;
+    let conflict = merge_against(&doc, base, &body);
+    ([(header::ETAG, etag(doc.revision))], axum::Json(conflict)).into_response()
+}
+
+/// Body of a 409 response: what is on the server now, and how the rejected text merges with it
+#[derive(Serialize)]
+struct ConflictBody {
+    revision: u64,
+    content: String,
+    /// False when the client's base revision is too old to reconstruct or comes
+    /// from an earlier server process; the merge then treats the current content as the base
+    base_available: bool,
+    merge: crate::diff::Merge,
+}
+
+fn merge_against(doc: &Document, base_revision: Option<u64>, ours: &str) -> ConflictBody {
+    let base = base_revision.and_then(|revision| doc.content_at(revision));
+    let base_available = base.is_some();
+    let base = base.unwrap_or_else(|| doc.content.clone());
+
+    ConflictBody {
+        revision: doc.revision,
+        content: doc.content.clone(),
+        base_available,
+        merge: crate::diff::merge3(&base, ours, &doc.content),
+    }
+}
+
+/// Random per-process prefix for ETags. Revisions restart at 0 with every
+/// server, so an ETag from an earlier run must not match a revision of this one
+fn epoch() -> &'static str {
+    static EPOCH: OnceLock<String> = OnceLock::new();
+    EPOCH.get_or_init(|| {
+        let mut bytes = [0u8; 8];
+        if let Err(e) = getrandom::fill(&mut bytes) {
+            eprintln!("Error: Could not generate an ETag epoch: {}", e);
+            std::process::exit(1);
+        }
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    })
+}
+
+fn etag(revision: u64) -> String {
+    format!("\"{}-{}\"", epoch(), revision)
+}
+
+/// A parsed If-Match header
+#[derive(Debug, PartialEq)]
+enum IfMatch {
+    /// `*`: write whatever the current revision is
+    Any,
+    Revision(u64),
+    /// A well-formed ETag from an earlier server process
+    OtherProcess,
+}
+
+fn parse_if_match(value: &HeaderValue) -> Option<IfMatch> {
+    let value = value.to_str().ok()?.trim();
+    if value == "*" {
+        return Some(IfMatch::Any);
+    }
+    let value = value.strip_prefix("W/").unwrap_or(value);
+    let (tag_epoch, revision) = value.trim_matches('"').split_once('-')?;
+    let revision = revision.parse().ok()?;
+    if tag_epoch == epoch() {
+        Some(IfMatch::Revision(revision))
+    } else {
+        Some(IfMatch::OtherProcess)
+    }
+}
 
-    Ok(StatusCode::OK)
+/// Preview HTML for the editor's current text, so the page needs no JS markdown library
+async fn render_preview(body: String) -> Html<String> {
+    Html(crate::render::to_html(&body))
 }
 
-async fn get_validation(State(state): State<AppState>) -> impl IntoResponse {
-    let content = state.content.read().await;
-    let bp = crate::parser::parse(&content, state.file_path.clone());
-    let results = crate::validator::validate(&bp);
+async fn get_validation(Doc(served): Doc) -> impl IntoResponse {
+    let doc = served.doc.read().await;
+    let bp = crate::parser::parse(&doc.content, served.file_path.clone());
+    let results = crate::validator::validate(&bp, &served.config);
 
     axum::Json(results)
 }
 
+/// Headings and task positions, for the outline panel and jump palette
+#[derive(Serialize)]
+struct Outline {
+    headings: Vec<Heading>,
+    tasks: Vec<OutlineTask>,
+}
+
+#[derive(Serialize)]
+struct OutlineTask {
+    number: Option<usize>,
+    status: TaskStatus,
+    text: String,
+    line: usize,
+}
+
+async fn get_outline(Doc(served): Doc) -> impl IntoResponse {
+    let doc = served.doc.read().await;
+    let content = &doc.content;
+    let bp = crate::parser::parse(content, served.file_path.clone());
+    let numbers = bp.tasks.all_active_numbered();
+
+    let mut tasks = Vec::new();
+    for items in [&bp.tasks.in_progress, &bp.tasks.next_up, &bp.tasks.icebox, &bp.tasks.done] {
+        for task in items {
+            let Some(line) = crate::parser::task_line(content, task) else { continue };
+            tasks.push(OutlineTask {
+                number: numbers.iter().find(|(_, t)| std::ptr::eq(*t, task)).map(|(n, _)| *n),
+                status: task.status,
+                text: task.text.clone(),
+                line,
+            });
+        }
+    }
+
+    axum::Json(Outline {
+        headings: crate::parser::outline(content),
+        tasks,
+    })
+}
+
+/// One lane of the task board
+#[derive(Serialize)]
+struct Lane<T> {
+    status: TaskStatus,
+    name: &'static str,
+    tasks: Vec<T>,
+}
+
+#[derive(Serialize)]
+struct BoardTask {
+    /// Task number as used by `nira task`; done tasks are not numbered
+    number: Option<usize>,
+    #[serde(flatten)]
+    task: TaskItem,
+}
+
+#[derive(Serialize)]
+struct Board {
+    revision: u64,
+    lanes: Vec<Lane<BoardTask>>,
+}
+
+/// A card on the combined board, with the blueprint it belongs to
+#[derive(Serialize)]
+struct WorkspaceTask {
+    doc: String,
+    doc_name: String,
+    #[serde(flatten)]
+    task: BoardTask,
+}
+
+#[derive(Deserialize)]
+struct TaskPath {
+    n: usize,
+}
+
+#[derive(Deserialize)]
+struct NewTask {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct MoveRequest {
+    to: TaskStatus,
+}
+
+fn board(doc: &Document, file_path: &FsPath) -> Board {
+    let bp = crate::parser::parse(&doc.content, file_path.to_path_buf());
+    let numbers = bp.tasks.all_active_numbered();
+    let number_of = |task: &TaskItem| {
+        numbers
+            .iter()
+            .find(|(_, t)| std::ptr::eq(*t, task))
+            .map(|(n, _)| *n)
+    };
+
+    let lanes = [
+        (TaskStatus::Icebox, &bp.tasks.icebox),
+        (TaskStatus::NextUp, &bp.tasks.next_up),
+        (TaskStatus::InProgress, &bp.tasks.in_progress),
+        (TaskStatus::Done, &bp.tasks.done),
+    ]
+    .into_iter()
+    .map(|(status, tasks)| Lane {
+        status,
+        name: status.section_name(),
+        tasks: tasks
+            .iter()
+            .map(|task| BoardTask {
+                number: number_of(task),
+                task: task.clone(),
+            })
+            .collect(),
+    })
+    .collect();
+
+    Board {
+        revision: doc.revision,
+        lanes,
+    }
+}
+
+async fn get_tasks(Doc(served): Doc) -> impl IntoResponse {
+    let doc = served.doc.read().await;
+    axum::Json(board(&doc, &served.file_path))
+}
+
+/// Every blueprint's lanes merged into one board
+async fn get_workspace_tasks(State(state): State<AppState>) -> impl IntoResponse {
+    let mut lanes: Vec<Lane<WorkspaceTask>> = Vec::new();
+    for served in state.docs.iter() {
+        let doc = served.doc.read().await;
+        let doc_name = crate::parser::parse(&doc.content, served.file_path.clone())
+            .project_name
+            .unwrap_or_else(|| served.display_path.clone());
+
+        for (i, lane) in board(&doc, &served.file_path).lanes.into_iter().enumerate() {
+            if lanes.len() <= i {
+                lanes.push(Lane {
+                    status: lane.status,
+                    name: lane.name,
+                    tasks: Vec::new(),
+                });
+            }
+            lanes[i].tasks.extend(lane.tasks.into_iter().map(|task| WorkspaceTask {
+                doc: served.id.clone(),
+                doc_name: doc_name.clone(),
+                task,
+            }));
+        }
+    }
+    axum::Json(serde_json::json!({ "lanes": lanes }))
+}
+
+async fn post_task(Doc(served): Doc, axum::Json(new): axum::Json<NewTask>) -> Response {
+    edit_tasks(&served, |content| crate::tasks::insert_task(content, new.text.trim())).await
+}
+
+async fn move_task(
+    Doc(served): Doc,
+    Path(TaskPath { n }): Path<TaskPath>,
+    axum::Json(request): axum::Json<MoveRequest>,
+) -> Response {
+    edit_tasks(&served, |content| {
+        crate::tasks::relocate_task_in(content, n, request.to).map(|(content, _)| content)
+    })
+    .await
+}
+
+async fn patch_task(
+    Doc(served): Doc,
+    Path(TaskPath { n }): Path<TaskPath>,
+    axum::Json(update): axum::Json<crate::tasks::TaskUpdate>,
+) -> Response {
+    edit_tasks(&served, |content| {
+        crate::tasks::update_task_in(content, n, &update).map(|(content, _)| content)
+    })
+    .await
+}
+
+/// Run a `nira task` edit against the live document, commit it as a normal edit
+/// so tabs see it in the markdown pane, and reply with the updated board
+async fn edit_tasks(
+    served: &ServedDoc,
+    edit: impl FnOnce(&str) -> std::io::Result<String>,
+) -> Response {
+    let mut doc = served.doc.write().await;
+
+    let new_content = match edit(&doc.content) {
+        Ok(content) => content,
+        Err(e) => {
+            let status = match e.kind() {
+                std::io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+                _ => StatusCode::UNPROCESSABLE_ENTITY,
+            };
+            return (status, format!("{}\n", e)).into_response();
+        }
+    };
+
+    if let Some((revision, op)) = doc.replace(&new_content, "board") {
+        let _ = served.broadcast_tx.send(ServerMessage::Edit {
+            revision,
+            op,
+            origin: "board".to_string(),
+            seq: 0,
+        });
+        served.save_notify.notify_one();
+    }
+
+    axum::Json(board(&doc, &served.file_path)).into_response()
+}
+
+#[derive(Deserialize)]
+struct RevPath {
+    rev: u64,
+}
+
+async fn get_history(Doc(served): Doc) -> Response {
+    match crate::history::list(&served.file_path) {
+        Ok(snapshots) => axum::Json(snapshots).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{}\n", e)).into_response(),
+    }
+}
+
+/// A snapshot and its line diff against the current content
+async fn get_snapshot(Doc(served): Doc, Path(RevPath { rev }): Path<RevPath>) -> Response {
+    let content = match crate::history::read(&served.file_path, rev) {
+        Ok(content) => content,
+        Err(e) => return (StatusCode::NOT_FOUND, format!("{}\n", e)).into_response(),
+    };
+    let doc = served.doc.read().await;
+    let diff = crate::diff::diff_lines(&content, &doc.content);
+    axum::Json(serde_json::json!({ "rev": rev, "content": content, "diff": diff })).into_response()
+}
+
+/// Make a snapshot the current content, as an ordinary edit every tab receives
+async fn restore_snapshot(Doc(served): Doc, Path(RevPath { rev }): Path<RevPath>) -> Response {
+    let content = match crate::history::read(&served.file_path, rev) {
+        Ok(content) => content,
+        Err(e) => return (StatusCode::NOT_FOUND, format!("{}\n", e)).into_response(),
+    };
+
+    let mut doc = served.doc.write().await;
+    if let Some((revision, op)) = doc.replace(&content, "restore") {
+        let _ = served.broadcast_tx.send(ServerMessage::Edit {
+            revision,
+            op,
+            origin: "restore".to_string(),
+            seq: 0,
+        });
+        served.save_notify.notify_one();
+    }
+    (StatusCode::OK, [(header::ETAG, etag(doc.revision))]).into_response()
+}
+
 async fn websocket_handler(
     ws: WebSocketUpgrade,
+    Query(params): Query<ConnectParams>,
     State(state): State<AppState>,
+    Doc(served): Doc,
 ) -> Response {
-    ws.on_upgrade(|socket| websocket_connection(socket, state))
+    let client_id = params.client.unwrap_or_else(|| {
+        format!("tab-{}", state.next_client.fetch_add(1, Ordering::Relaxed))
+    });
+    let changes_rx = state.changes_tx.subscribe();
+    ws.on_upgrade(move |socket| websocket_connection(socket, served, changes_rx, client_id, params.since))
 }
 
-async fn websocket_connection(mut socket: WebSocket, state: AppState) {
+async fn websocket_connection(
+    mut socket: WebSocket,
+    served: Arc<ServedDoc>,
+    mut changes_rx: broadcast::Receiver<String>,
+    client_id: String,
+    since: Option<u64>,
+) {
     use futures::stream::StreamExt;
 
-    let mut broadcast_rx = state.broadcast_tx.subscribe();
+    // Subscribe before reading the document so no edit falls between catch-up and live updates
+    let mut broadcast_rx = served.broadcast_tx.subscribe();
+
+    // Catch the tab up: replay missed edits if we still have them, else send everything
+    let catch_up = {
+        let doc = served.doc.read().await;
+        let mut messages = Vec::new();
+        match since.and_then(|since| doc.revisions_since(since)) {
+            Some(revisions) => {
+                for (revision, committed) in revisions {
+                    messages.push(ServerMessage::Edit {
+                        revision,
+                        op: committed.op,
+                        origin: committed.client_id,
+                        seq: committed.seq,
+                    });
+                }
+            }
+            None => messages.push(ServerMessage::Init {
+                revision: doc.revision,
+                content: doc.content.clone(),
+            }),
+        }
+        messages.push(ServerMessage::Ready {
+            revision: doc.revision,
+            epoch: epoch(),
+        });
+        messages.push(diagnostics_message(&served, &doc));
+        (messages, doc.revision)
+    };
+    let (messages, caught_up_to) = catch_up;
 
-    // Send initial content
-    let content = state.content.read().await.clone();
-    if socket.send(Message::Text(content.into())).await.is_err() {
-        return;
+    for message in messages {
+        if send_message(&mut socket, &message).await.is_err() {
+            return;
+        }
     }
 
     loop {
         tokio::select! {
             // Receive from client
             Some(Ok(msg)) = socket.next() => {
-                if let Message::Text(_text) = msg {
-                    // Client sent updated content - ignore for now
-                    // (we rely on PUT /api/blueprint for saves)
+                let Message::Text(text) = msg else { continue };
+                let reply = match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(ClientMessage::Edit { revision, op, seq }) => {
+                        handle_edit(&served, &client_id, revision, op, seq).await
+                    }
+                    Err(e) => {
+                        eprintln!("Ignoring malformed message from {}: {}", client_id, e);
+                        None
+                    }
+                };
+                if let Some(reply) = reply {
+                    if send_message(&mut socket, &reply).await.is_err() {
+                        break;
+                    }
                 }
             }
 
-            // Receive from broadcast (file changes)
-            Ok(content) = broadcast_rx.recv() => {
-                if socket.send(Message::Text(content.into())).await.is_err() {
+            // Receive from broadcast (edits from other tabs and from disk)
+            result = broadcast_rx.recv() => {
+                let message = match result {
+                    Ok(message) => message,
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        // Too far behind to replay from the channel; start over from full content
+                        let doc = served.doc.read().await;
+                        ServerMessage::Init { revision: doc.revision, content: doc.content.clone() }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+
+                // Edits already included in the catch-up were queued on the channel too
+                if let ServerMessage::Edit { revision, .. } = &message {
+                    if *revision <= caught_up_to {
+                        continue;
+                    }
+                }
+
+                if send_message(&mut socket, &message).await.is_err() {
+                    break;
+                }
+            }
+
+            // Other blueprints in the workspace changed
+            Ok(doc) = changes_rx.recv() => {
+                if doc != served.id && send_message(&mut socket, &ServerMessage::DocChanged { doc }).await.is_err() {
                     break;
                 }
             }
@@ -159,10 +968,98 @@ async fn websocket_connection(mut socket: WebSocket, state: AppState) {
     }
 }
 
-async fn watch_file_changes(
-    file_path: PathBuf,
-    state: AppState,
-) -> notify::Result<()> {
+/// Commit a tab's edit. Returns a message for that tab only; successful edits
+/// reach every tab (the sender included) through the broadcast channel.
+async fn handle_edit(
+    served: &ServedDoc,
+    client_id: &str,
+    revision: u64,
+    op: TextOperation,
+    seq: u64,
+) -> Option<ServerMessage> {
+    let mut doc = served.doc.write().await;
+
+    match doc.apply_edit(revision, op, client_id, seq) {
+        Ok(Applied::Committed { revision, op }) => {
+            let _ = served.broadcast_tx.send(ServerMessage::Edit {
+                revision,
+                op,
+                origin: client_id.to_string(),
+                seq,
+            });
+            served.save_notify.notify_one();
+            None
+        }
+        Ok(Applied::Duplicate { revision }) => Some(ServerMessage::Ack { revision, seq }),
+        Err(e) => Some(ServerMessage::Conflict {
+            revision: doc.revision,
+            content: doc.content.clone(),
+            message: e.to_string(),
+        }),
+    }
+}
+
+async fn send_message(socket: &mut WebSocket, message: &ServerMessage) -> Result<(), axum::Error> {
+    let json = serde_json::to_string(message).unwrap_or_default();
+    socket.send(Message::Text(json.into())).await
+}
+
+/// Write the document to disk after edits settle
+async fn save_loop(served: Arc<ServedDoc>) {
+    loop {
+        served.save_notify.notified().await;
+        tokio::time::sleep(SAVE_DELAY).await;
+
+        let mut doc = served.doc.write().await;
+        if !doc.is_dirty() {
+            continue;
+        }
+
+        // Someone else changed the file since our last sync; the watcher merges it and writes back
+        if let Ok(on_disk) = std::fs::read_to_string(&served.file_path) {
+            if on_disk != doc.disk_content {
+                continue;
+            }
+        }
+
+        match std::fs::write(&served.file_path, &doc.content) {
+            Ok(_) => served.mark_saved(&mut doc),
+            Err(e) => eprintln!("Error writing file: {}", e),
+        }
+    }
+}
+
+fn diagnostics_message(served: &ServedDoc, doc: &Document) -> ServerMessage {
+    let bp = crate::parser::parse(&doc.content, served.file_path.clone());
+    ServerMessage::Diagnostics {
+        revision: doc.revision,
+        diagnostics: crate::validator::diagnostics(&bp, &served.config),
+    }
+}
+
+/// Recompute diagnostics once edits (from any origin) pause, broadcast them, and
+/// tell the rest of the workspace this document changed
+async fn lint_loop(served: Arc<ServedDoc>, changes_tx: broadcast::Sender<String>) {
+    let mut rx = served.broadcast_tx.subscribe();
+    loop {
+        match rx.recv().await {
+            Ok(ServerMessage::Edit { .. }) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Ok(_) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+
+        // Let a burst of typing finish; anything queued meanwhile is covered by this run
+        tokio::time::sleep(LINT_DELAY).await;
+        while rx.try_recv().is_ok() {}
+
+        let message = diagnostics_message(&served, &*served.doc.read().await);
+        let _ = served.broadcast_tx.send(message);
+        let _ = changes_tx.send(served.id.clone());
+    }
+}
+
+async fn watch_file_changes(served: Arc<ServedDoc>) -> notify::Result<()> {
+    let file_path = served.file_path.clone();
     let (tx, mut rx) = tokio::sync::mpsc::channel(100);
 
     let mut watcher = RecommendedWatcher::new(
@@ -174,26 +1071,80 @@ async fn watch_file_changes(
         Config::default(),
     )?;
 
-    watcher.watch(&file_path, RecursiveMode::NonRecursive)?;
+    // Watch the directory rather than the file: editors that save by writing a
+    // temp file and renaming it over the original replace the watched inode
+    let dir = match file_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
 
     while let Some(event) = rx.recv().await {
-        use notify::EventKind;
-
-        match event.kind {
-            EventKind::Modify(_) | EventKind::Create(_) => {
-                // File was modified externally
-                if let Ok(content) = std::fs::read_to_string(&file_path) {
-                    let mut state_content = state.content.write().await;
-                    *state_content = content.clone();
-                    drop(state_content);
-
-                    // Broadcast to all WebSocket clients
-                    let _ = state.broadcast_tx.send(content);
+        if !touches_file(&event, &file_path) {
+            continue;
+        }
+
+        // Debounce: wait until events stop for WRITE_SETTLE (bounded by MAX_SETTLE)
+        // so a half-written file is never merged as a mass delete
+        let deadline = tokio::time::Instant::now() + MAX_SETTLE;
+        while let Ok(Some(_)) = tokio::time::timeout_at(
+            deadline.min(tokio::time::Instant::now() + WRITE_SETTLE),
+            rx.recv(),
+        )
+        .await
+        {}
+
+        // Hold the lock while reading so we never see our own half-written save
+        let mut doc = served.doc.write().await;
+        let Ok(on_disk) = std::fs::read_to_string(&file_path) else {
+            // Mid-rename or deleted; the next event brings the new file
+            continue;
+        };
+
+        // Our own save (or a write of what we already have): nothing to tell the tabs
+        if on_disk == doc.content {
+            if doc.is_dirty() {
+                served.mark_saved(&mut doc);
+            }
+            continue;
+        }
+
+        // File was modified externally: merge it with edits made since the last sync
+        match doc.merge_disk(&on_disk) {
+            Ok(Some((revision, op))) => {
+                let _ = served.broadcast_tx.send(ServerMessage::Edit {
+                    revision,
+                    op,
+                    origin: "disk".to_string(),
+                    seq: 0,
+                });
+
+                // Unsaved browser edits were merged in; put the result back on disk
+                if doc.content != on_disk {
+                    if let Err(e) = std::fs::write(&file_path, &doc.content) {
+                        eprintln!("Error writing file: {}", e);
+                        continue;
+                    }
                 }
+                served.mark_saved(&mut doc);
             }
-            _ => {}
+            Ok(None) => {}
+            Err(e) => eprintln!("Could not merge external change: {}", e),
         }
     }
 
     Ok(())
 }
+
+/// True if the event creates, modifies or renames something onto the blueprint file
+fn touches_file(event: &Event, file_path: &std::path::Path) -> bool {
+    use notify::EventKind;
+
+    if !matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
+        return false;
+    }
+    event
+        .paths
+        .iter()
+        .any(|p| p.file_name() == file_path.file_name())
+}
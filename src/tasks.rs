@@ -1,6 +1,7 @@
-use crate::blueprint::{Blueprint, TaskStatus};
+use crate::blueprint::{Blueprint, TaskItem, TaskStatus};
+use serde::Deserialize;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub fn list_tasks(bp: &Blueprint) {
     println!("Tasks for: {}\n", bp.path.display());
@@ -58,6 +59,14 @@ pub fn list_tasks(bp: &Blueprint) {
 
 pub fn add_task(path: &Path, description: &str) -> io::Result<()> {
     let content = std::fs::read_to_string(path)?;
+    std::fs::write(path, insert_task(&content, description)?)
+}
+
+/// Add a task to the end of NEXT UP, returning the new blueprint text
+pub fn insert_task(content: &str, description: &str) -> io::Result<String> {
+    if description.trim().is_empty() || description.contains('\n') {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Task text must be a single non-empty line"));
+    }
 
     // Find the NEXT UP section
     let lines: Vec<&str> = content.lines().collect();
@@ -82,6 +91,11 @@ pub fn add_task(path: &Path, description: &str) -> io::Result<()> {
                 insert_idx += 1;
             }
 
+            // Insert after the last task rather than after the blank lines before the next lane
+            while insert_idx > idx + 1 && lines[insert_idx - 1].trim().is_empty() {
+                insert_idx -= 1;
+            }
+
             insert_line = Some(insert_idx);
             break;
         }
@@ -97,145 +111,211 @@ pub fn add_task(path: &Path, description: &str) -> io::Result<()> {
         description
     );
 
-    // Insert the task
+    // Insert the task, keeping a blank line before whatever follows it
     let mut new_lines = lines[..insert_line].to_vec();
     new_lines.extend(task_entry.lines());
+    if lines.get(insert_line).is_some_and(|l| !l.trim().is_empty()) {
+        new_lines.push("");
+    }
     new_lines.extend(&lines[insert_line..]);
 
-    let new_content = new_lines.join("\n");
+    Ok(new_lines.join("\n"))
+}
+
+/// Move an active task to another section. Returns the task as it was before the move.
+pub fn move_task(path: &Path, task_num: usize, target: TaskStatus) -> io::Result<TaskItem> {
+    let content = std::fs::read_to_string(path)?;
+    let (new_content, task) = move_task_in(&content, task_num, target)?;
     std::fs::write(path, new_content)?;
+    Ok(task)
+}
 
-    println!("✓ Added task to NEXT UP: {}", description);
-    Ok(())
+/// Move an active task to another section of `content`, returning the new
+/// blueprint text and the task as it was before the move. The task gets the
+/// metadata lines of its new lane, filled from what it already has; moving it to
+/// the lane it is already in changes nothing.
+pub fn move_task_in(content: &str, task_num: usize, target: TaskStatus) -> io::Result<(String, TaskItem)> {
+    relocate(content, task_num, target, false)
 }
 
-pub fn move_task(path: &Path, task_num: usize, target: TaskStatus) -> io::Result<()> {
-    let content = std::fs::read_to_string(path)?;
-    let bp = crate::parser::parse(&content, path.to_path_buf());
+/// Like `move_task_in`, but the task keeps its own metadata lines, for dragging
+/// tasks between lanes on the board
+pub fn relocate_task_in(content: &str, task_num: usize, target: TaskStatus) -> io::Result<(String, TaskItem)> {
+    relocate(content, task_num, target, true)
+}
 
-    // Find the task
-    let active_tasks = bp.tasks.all_active_numbered();
-    let (_, task) = active_tasks.iter()
-        .find(|(num, _)| *num == task_num)
-        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Task #{} not found", task_num)))?;
-
-    // Find the target section
-    let target_section = match target {
-        TaskStatus::Done => "### DONE",
-        TaskStatus::InProgress => "### IN PROGRESS",
-        TaskStatus::NextUp => "### NEXT UP",
-        TaskStatus::Icebox => "### ICEBOX",
+/// The task's lines as they read in lane `target`
+fn lane_entry(task: &TaskItem, target: TaskStatus) -> Vec<String> {
+    let field = |value: &Option<String>| value.as_deref().unwrap_or("").to_string();
+    let (depends_on, approach) = match task.approach.as_deref().and_then(|a| a.strip_prefix("Depends on: ")) {
+        Some(depends_on) => (depends_on.to_string(), String::new()),
+        None => (String::new(), field(&task.approach)),
+    };
+    let lines = match target {
+        TaskStatus::Done => vec![format!("- [x] {}", task.text)],
+        TaskStatus::InProgress => vec![
+            format!("- [ ] **{}**", task.text),
+            format!("  - **Context:** {}", field(&task.context)),
+            "  - **Blocked?** no".to_string(),
+            format!("  - **Files:** {}", field(&task.files)),
+        ],
+        TaskStatus::NextUp => vec![
+            format!("- [ ] **{}**", task.text),
+            format!("  - **Depends on:** {}", depends_on),
+            format!("  - **Files:** {}", field(&task.files)),
+            format!("  - **Approach:** {}", approach),
+        ],
+        TaskStatus::Icebox => vec![format!("- [ ] {}", task.text)],
     };
+    lines.into_iter().map(|line| line.trim_end().to_string()).collect()
+}
 
-    let lines: Vec<&str> = content.lines().collect();
+fn relocate(content: &str, task_num: usize, target: TaskStatus, keep_metadata: bool) -> io::Result<(String, TaskItem)> {
+    let bp = crate::parser::parse(content, PathBuf::new());
+    let task = find_active(&bp, task_num)?;
+    if task.status == target {
+        return Ok((content.to_string(), task.clone()));
+    }
 
-    // Find and remove the task from its current location
-    let mut new_lines = Vec::new();
-    let mut skip_until = None;
-    let mut found_task = false;
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let start = task_start(&lines, content, task)?;
+    let end = metadata_end(&lines, start);
 
-    for (idx, line) in lines.iter().enumerate() {
-        if let Some(skip_idx) = skip_until {
-            if idx < skip_idx {
-                continue;
-            } else {
-                skip_until = None;
-            }
-        }
+    // Take the task out, without leaving two blank lines where it was
+    let mut block: Vec<String> = lines.drain(start..end).collect();
+    if start > 0 && lines[start - 1].trim().is_empty() && lines.get(start).is_some_and(|l| l.trim().is_empty()) {
+        lines.remove(start);
+    }
 
-        // Check if this line is our task
-        if line.trim_start().starts_with("- [") && line.contains(&task.text) {
-            found_task = true;
-            // Skip this line and any following metadata lines
-            let mut end_idx = idx + 1;
-            while end_idx < lines.len() {
-                let next_line = lines[end_idx].trim_start();
-                if next_line.starts_with("- [") || next_line.starts_with("###") {
-                    break;
-                }
-                if !next_line.is_empty() && next_line.starts_with("- ") {
-                    end_idx += 1;
-                } else if next_line.is_empty() {
-                    end_idx += 1;
-                } else {
-                    break;
-                }
-            }
-            skip_until = Some(end_idx);
-            continue;
-        }
+    if keep_metadata {
+        let checkbox = if target == TaskStatus::Done { "[x]" } else { "[ ]" };
+        let indent = block[0].len() - block[0].trim_start().len();
+        block[0] = format!("{}- {}{}", &block[0][..indent], checkbox, &block[0][indent + 5..]);
+    } else {
+        block = lane_entry(task, target);
+    }
+
+    // Insert at the top of the target lane, after the blank lines under its heading
+    let target_section = format!("### {}", target.section_name());
+    let heading = lines
+        .iter()
+        .position(|line| line.trim().starts_with(&target_section))
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Could not find '{}' section in blueprint", target_section))
+        })?;
+    let mut at = heading + 1;
+    while at < lines.len() && lines[at].trim().is_empty() {
+        at += 1;
+    }
 
-        new_lines.push(*line);
+    // Separate the task from what follows, unless it joins a list of one-line tasks
+    let next_is_plain_task = lines.get(at).is_some_and(|l| l.trim_start().starts_with("- ["));
+    if at < lines.len() && !(next_is_plain_task && block.len() == 1) {
+        block.push(String::new());
     }
+    lines.splice(at..at, block);
 
-    if !found_task {
-        return Err(io::Error::new(io::ErrorKind::NotFound, "Task not found in file"));
+    let mut new_content = lines.join("\n");
+    if content.ends_with('\n') {
+        new_content.push('\n');
     }
+    Ok((new_content, task.clone()))
+}
 
-    // Find target section and insert
-    let mut final_lines: Vec<String> = Vec::new();
-    let mut inserted = false;
+/// Fields of a task to change; `None` leaves a field as it is
+#[derive(Debug, Default, Deserialize)]
+pub struct TaskUpdate {
+    pub text: Option<String>,
+    pub context: Option<String>,
+    pub files: Option<String>,
+    pub approach: Option<String>,
+}
 
-    for (idx, line) in new_lines.iter().enumerate() {
-        final_lines.push(line.to_string());
+/// Edit an active task in the file, returning the task as it reads afterwards
+pub fn edit_task(path: &Path, task_num: usize, update: &TaskUpdate) -> io::Result<TaskItem> {
+    let content = std::fs::read_to_string(path)?;
+    let (new_content, task) = update_task_in(&content, task_num, update)?;
+    std::fs::write(path, new_content)?;
+    Ok(task)
+}
 
-        if line.trim().starts_with(target_section) && !inserted {
-            // Skip empty lines
-            let mut insert_idx = idx + 1;
-            while insert_idx < new_lines.len() && new_lines[insert_idx].trim().is_empty() {
-                final_lines.push(new_lines[insert_idx].to_string());
-                insert_idx += 1;
-            }
+/// Edit an active task's title and metadata lines in place, returning the new
+/// blueprint text and the task as it reads afterwards
+pub fn update_task_in(content: &str, task_num: usize, update: &TaskUpdate) -> io::Result<(String, TaskItem)> {
+    let bp = crate::parser::parse(content, PathBuf::new());
+    let task = find_active(&bp, task_num)?;
 
-            // Format task based on target status
-            let task_text = match target {
-                TaskStatus::Done => {
-                    format!("- [x] {}", task.text)
-                }
-                TaskStatus::InProgress => {
-                    let mut text = format!("- [ ] **{}**", task.text);
-                    text.push_str(&format!("\n  - **Context:** {}", task.context.as_deref().unwrap_or("")));
-                    text.push_str(&format!("\n  - **Blocked?** no"));
-                    text.push_str(&format!("\n  - **Files:** {}", task.files.as_deref().unwrap_or("")));
-                    text
-                }
-                TaskStatus::NextUp => {
-                    let mut text = format!("- [ ] **{}**", task.text);
-                    text.push_str(&format!("\n  - **Depends on:** {}", task.approach.as_deref().unwrap_or("")));
-                    text.push_str(&format!("\n  - **Files:** {}", task.files.as_deref().unwrap_or("")));
-                    text.push_str(&format!("\n  - **Approach:** "));
-                    text
-                }
-                TaskStatus::Icebox => {
-                    format!("- [ ] {}", task.text)
-                }
-            };
+    if let Some(text) = &update.text {
+        if text.trim().is_empty() || text.contains('\n') {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Task text must be a single non-empty line"));
+        }
+    }
 
-            for task_line in task_text.lines() {
-                final_lines.push(task_line.to_string());
-            }
-            final_lines.push(String::new());
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let start = task_start(&lines, content, task)?;
 
-            inserted = true;
+    if let Some(text) = &update.text {
+        lines[start] = lines[start].replacen(&task.text, text.trim(), 1);
+    }
 
-            // Skip ahead past the lines we already added
-            let skip_count = insert_idx - idx - 1;
-            for _ in 0..skip_count {
-                // Already added these empty lines
+    let mut end = metadata_end(&lines, start);
+
+    let fields = [
+        ("Context", &update.context),
+        ("Files", &update.files),
+        ("Approach", &update.approach),
+    ];
+    for (label, value) in fields {
+        let Some(value) = value else { continue };
+        let prefix = format!("- **{}:**", label);
+        let line = format!("  {} {}", prefix, value.trim()).trim_end().to_string();
+        match (start + 1..end).find(|&i| lines[i].trim_start().starts_with(&prefix)) {
+            Some(i) => lines[i] = line,
+            None => {
+                lines.insert(end, line);
+                end += 1;
             }
         }
     }
 
-    let new_content = final_lines.join("\n");
-    std::fs::write(path, new_content)?;
+    let mut new_content = lines.join("\n");
+    if content.ends_with('\n') {
+        new_content.push('\n');
+    }
 
-    let status_name = match target {
-        TaskStatus::Done => "DONE",
-        TaskStatus::InProgress => "IN PROGRESS",
-        TaskStatus::NextUp => "NEXT UP",
-        TaskStatus::Icebox => "ICEBOX",
-    };
+    let bp = crate::parser::parse(&new_content, PathBuf::new());
+    let updated = find_active(&bp, task_num)?.clone();
+    Ok((new_content, updated))
+}
+
+/// Index into `lines` of the task's checkbox line, located by its position rather
+/// than its text so tasks with similar titles can't be confused
+fn task_start(lines: &[String], content: &str, task: &TaskItem) -> io::Result<usize> {
+    crate::parser::task_line(content, task)
+        .map(|line| line - 1)
+        .filter(|&idx| lines.get(idx).is_some_and(|l| l.trim_start().starts_with("- [")))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Task not found in file"))
+}
 
-    println!("✓ Moved task #{} to {}: {}", task_num, status_name, task.text);
-    Ok(())
+/// End (exclusive) of the metadata lines under the task at `start`: they belong to
+/// the task until the next task, heading or blank line
+fn metadata_end(lines: &[String], start: usize) -> usize {
+    let mut end = start + 1;
+    while end < lines.len() {
+        let line = lines[end].trim_start();
+        if line.is_empty() || line.starts_with("- [") || line.starts_with('#') || !line.starts_with("- ") {
+            break;
+        }
+        end += 1;
+    }
+    end
+}
+
+fn find_active(bp: &Blueprint, task_num: usize) -> io::Result<&TaskItem> {
+    bp.tasks
+        .all_active_numbered()
+        .into_iter()
+        .find(|(num, _)| *num == task_num)
+        .map(|(_, task)| task)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Task #{} not found", task_num)))
 }
@@ -1,89 +1,716 @@
+use crate::blueprint::Severity;
+use regex::Regex;
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-
-/// Find the templates directory. Check multiple locations:
-/// 1. <exe_dir>/templates/ (relative to nira.exe - primary location)
-/// 2. ./templates/ (relative to CWD - for development convenience)
-fn find_templates_dir() -> Option<PathBuf> {
-    // Try relative to executable first (primary location)
-    if let Ok(exe_path) = std::env::current_exe() {
-        if let Some(exe_dir) = exe_path.parent() {
-            let exe_templates = exe_dir.join("templates");
-            if exe_templates.exists() && exe_templates.is_dir() {
-                return Some(exe_templates);
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Templates compiled into the binary, so `nira init` works without a templates/ folder
+const BUILTIN_TEMPLATES: [(&str, &str); 7] = [
+    ("_task-queue", include_str!("../templates/_task-queue.md")),
+    ("constraints-first", include_str!("../templates/constraints-first.md")),
+    ("default", include_str!("../templates/default.md")),
+    ("game", include_str!("../templates/game.md")),
+    ("interface-driven", include_str!("../templates/interface-driven.md")),
+    ("minimal", include_str!("../templates/minimal.md")),
+    ("quick", include_str!("../templates/quick.md")),
+];
+
+/// A value a template asks for, declared in its header:
+///
+/// ```text
+/// <!-- Variables:
+/// AUTHOR: Who owns the architecture
+/// LICENSE: Project license | default: MIT | choices: MIT, Apache-2.0, GPL-3.0
+/// -->
+/// ```
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub name: String,
+    pub description: String,
+    pub default: Option<String>,
+    /// Allowed values; empty means anything goes
+    pub choices: Vec<String>,
+}
+
+/// Where a template was found, in search order: earlier sources shadow later ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// `.nira/templates/` in the project root (the nira.toml directory, else the repository root)
+    Project,
+    /// `$XDG_CONFIG_HOME/nira/templates/` (or `~/.config/nira/templates/`)
+    User,
+    /// A directory listed in `NIRA_TEMPLATE_PATH`
+    EnvPath,
+    /// `templates/` next to the nira executable
+    Executable,
+    /// `templates/` in the current directory
+    WorkingDir,
+    /// Compiled into the binary
+    Builtin,
+    /// Given directly with `--template-file` or `--file`
+    File,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Project => write!(f, "project"),
+            Source::User => write!(f, "user"),
+            Source::EnvPath => write!(f, "NIRA_TEMPLATE_PATH"),
+            Source::Executable => write!(f, "next to nira"),
+            Source::WorkingDir => write!(f, "./templates"),
+            Source::Builtin => write!(f, "built-in"),
+            Source::File => write!(f, "file"),
+        }
+    }
+}
+
+/// A template and where it came from
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    pub description: String,
+    pub source: Source,
+    /// File the template was read from; `None` for built-ins
+    pub path: Option<PathBuf>,
+    pub content: String,
+    /// Templates with the same name further down the search path, hidden by this one
+    pub shadows: Vec<(Source, Option<PathBuf>)>,
+}
+
+/// The project's template directory, `.nira/templates/` under the project root
+pub fn project_dir(root: &Path) -> PathBuf {
+    root.join(".nira").join("templates")
+}
+
+/// The user's template directory, `$XDG_CONFIG_HOME/nira/templates/`
+pub fn user_dir() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("nira").join("templates"))
+}
+
+/// Template directories in search order, for a project rooted at `root`
+pub fn search_path(root: &Path) -> Vec<(Source, PathBuf)> {
+    let mut dirs = vec![(Source::Project, project_dir(root))];
+
+    if let Some(dir) = user_dir() {
+        dirs.push((Source::User, dir));
+    }
+
+    if let Some(paths) = std::env::var_os("NIRA_TEMPLATE_PATH") {
+        for dir in std::env::split_paths(&paths).filter(|dir| !dir.as_os_str().is_empty()) {
+            dirs.push((Source::EnvPath, dir));
+        }
+    }
+
+    if let Some(exe_dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)) {
+        dirs.push((Source::Executable, exe_dir.join("templates")));
+    }
+    dirs.push((Source::WorkingDir, PathBuf::from("templates")));
+
+    // The same directory can be reachable twice (e.g. running nira from the folder it lives in)
+    let mut seen = Vec::new();
+    dirs.retain(|(_, dir)| match fs::canonicalize(dir) {
+        Ok(real) if seen.contains(&real) => false,
+        Ok(real) => {
+            seen.push(real);
+            true
+        }
+        Err(_) => true,
+    });
+
+    dirs
+}
+
+/// Every template on the search path plus the built-ins, including shadowed ones,
+/// in search order
+fn all_templates(root: &Path) -> Vec<Template> {
+    let mut templates = Vec::new();
+
+    for (source, dir) in search_path(root) {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        paths.sort();
+
+        for path in paths {
+            // Only process .md files
+            if path.extension().and_then(|s| s.to_str()) != Some("md") {
+                continue;
             }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+
+            templates.push(Template {
+                name: name.to_string(),
+                description: extract_description(&content),
+                source,
+                path: Some(path.clone()),
+                content,
+                shadows: Vec::new(),
+            });
         }
     }
 
-    // Fallback: try current directory (useful during development)
-    let cwd_templates = PathBuf::from("templates");
-    if cwd_templates.exists() && cwd_templates.is_dir() {
-        return Some(cwd_templates);
+    for (name, content) in BUILTIN_TEMPLATES {
+        templates.push(Template {
+            name: name.to_string(),
+            description: extract_description(content),
+            source: Source::Builtin,
+            path: None,
+            content: content.to_string(),
+            shadows: Vec::new(),
+        });
     }
 
-    None
+    templates
 }
 
-/// Load a template by name from the templates/ directory
-pub fn get_template(name: &str) -> Option<String> {
-    let templates_dir = find_templates_dir()?;
-    let template_file = templates_dir.join(format!("{}.md", name));
+/// Find a template by name: the first match on the search path, then the built-ins
+pub fn find_template(name: &str, root: &Path) -> Option<Template> {
+    let mut candidates = all_templates(root).into_iter().filter(|t| t.name == name);
+    let mut template = candidates.next()?;
+    template.shadows = candidates.map(|t| (t.source, t.path)).collect();
+    Some(template)
+}
 
-    fs::read_to_string(template_file).ok()
+/// Read a template straight from a file, outside the search path
+pub fn from_file(path: &Path) -> io::Result<Template> {
+    let content = fs::read_to_string(path)?;
+    Ok(Template {
+        name: path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default(),
+        description: extract_description(&content),
+        source: Source::File,
+        path: Some(path.to_path_buf()),
+        content,
+        shadows: Vec::new(),
+    })
 }
 
-/// List all available templates by reading .md files from templates/
-pub fn list_templates() -> Vec<(String, String)> {
-    let Some(templates_dir) = find_templates_dir() else {
-        return vec![];
-    };
+/// All available templates sorted by name, each the one that wins on the search path,
+/// with the ones it shadows recorded in `shadows`. Fragments (names starting with `_`,
+/// meant to be included by other templates) are left out.
+pub fn list_templates(root: &Path) -> Vec<Template> {
+    let mut templates: Vec<Template> = Vec::new();
 
-    let Ok(entries) = fs::read_dir(&templates_dir) else {
-        return vec![];
-    };
+    for template in all_templates(root).into_iter().filter(|t| !t.name.starts_with('_')) {
+        match templates.iter_mut().find(|t| t.name == template.name) {
+            Some(winner) => winner.shadows.push((template.source, template.path)),
+            None => templates.push(template),
+        }
+    }
+
+    // Sort by name for consistent ordering
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+
+    templates
+}
 
-    let mut templates = vec![];
+/// `[source: path; shadows ...]` note for template listings
+pub fn describe_origin(template: &Template) -> String {
+    let mut note = match &template.path {
+        Some(path) => format!("{}: {}", template.source, path.display()),
+        None => template.source.to_string(),
+    };
+    if !template.shadows.is_empty() {
+        let shadowed: Vec<String> = template
+            .shadows
+            .iter()
+            .map(|(source, path)| match path {
+                Some(path) => format!("{} ({})", source, path.display()),
+                None => source.to_string(),
+            })
+            .collect();
+        note.push_str(&format!("; shadows {}", shadowed.join(", ")));
+    }
+    format!("[{}]", note)
+}
 
-    for entry in entries.flatten() {
-        let path = entry.path();
+/// Line ranges (start inclusive, end exclusive) of the `<!-- Variables: ... -->` blocks;
+/// a template that extends or includes others carries one block per template
+fn variables_blocks(content: &str) -> Vec<(usize, usize)> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut blocks = vec![];
+    let mut idx = 0;
+    while idx < lines.len() {
+        if lines[idx].trim().starts_with("<!-- Variables:") {
+            if let Some(len) = lines[idx..].iter().position(|line| line.contains("-->")) {
+                blocks.push((idx, idx + len + 1));
+                idx += len;
+            }
+        }
+        idx += 1;
+    }
+    blocks
+}
 
-        // Only process .md files
-        if path.extension().and_then(|s| s.to_str()) != Some("md") {
-            continue;
+/// Variables declared in the template's header blocks, in declaration order; the first
+/// declaration of a name wins
+pub fn variables(content: &str) -> Vec<Variable> {
+    let mut variables: Vec<Variable> = vec![];
+    for (start, end) in variables_blocks(content) {
+        for variable in parse_variables(content.lines().skip(start).take(end - start)) {
+            if !variables.iter().any(|v| v.name == variable.name) {
+                variables.push(variable);
+            }
         }
+    }
+    variables
+}
 
-        // Get template name from filename
-        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+fn parse_variables<'a>(block: impl Iterator<Item = &'a str>) -> Vec<Variable> {
+    let mut variables = vec![];
+    for line in block {
+        let line = line.trim().trim_start_matches("<!-- Variables:").trim_end_matches("-->").trim();
+        let Some((name, rest)) = line.split_once(':') else {
             continue;
         };
 
-        // Read file to extract description from first line comment
-        let description = if let Ok(content) = fs::read_to_string(&path) {
-            extract_description(&content)
-        } else {
-            format!("Template: {}", name)
+        let mut fields = rest.split('|');
+        let mut variable = Variable {
+            name: name.trim().to_string(),
+            description: fields.next().unwrap_or("").trim().to_string(),
+            default: None,
+            choices: vec![],
         };
+        for field in fields {
+            match field.trim().split_once(':') {
+                Some(("default", value)) => variable.default = Some(value.trim().to_string()),
+                Some(("choices", values)) => {
+                    variable.choices = values.split(',').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect()
+                }
+                _ => {}
+            }
+        }
+        variables.push(variable);
+    }
+    variables
+}
 
-        templates.push((name.to_string(), description));
+/// Fill in the template: drop the variables block and replace every `{NAME}` with its
+/// value. Fails if a value is not one of the variable's choices, or if any `{NAME}`
+/// has no value.
+pub fn render(content: &str, values: &HashMap<String, String>) -> io::Result<String> {
+    for variable in variables(content) {
+        if let Some(value) = values.get(&variable.name) {
+            if !variable.choices.is_empty() && !variable.choices.contains(value) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("'{}' is not a valid {} (choose from: {})", value, variable.name, variable.choices.join(", ")),
+                ));
+            }
+        }
     }
 
-    // Sort by name for consistent ordering
-    templates.sort_by(|a, b| a.0.cmp(&b.0));
+    let blocks = variables_blocks(content);
+    let body = if blocks.is_empty() {
+        content.to_string()
+    } else {
+        let mut lines: Vec<&str> = content.lines().collect();
+        for (start, end) in blocks.into_iter().rev() {
+            lines.drain(start..end);
+        }
+        join_lines(&lines, content.ends_with('\n'))
+    };
 
-    templates
+    let unknown: Vec<String> = placeholders(&body)
+        .into_iter()
+        .filter(|(name, _)| !values.contains_key(name))
+        .map(|(name, line)| format!("{{{}}} (line {})", name, line))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("No value for {}", unknown.join(", ")),
+        ));
+    }
+
+    Ok(placeholder_regex()
+        .replace_all(&body, |caps: &regex::Captures| values[&caps[1]].clone())
+        .into_owned())
+}
+
+fn placeholder_regex() -> Regex {
+    Regex::new(r"\{([A-Z][A-Z0-9_]*)\}").unwrap()
+}
+
+/// Every `{NAME}` in `content` with its 1-based line
+fn placeholders(content: &str) -> Vec<(String, usize)> {
+    let placeholder = placeholder_regex();
+    let mut found = vec![];
+    for (idx, line) in content.lines().enumerate() {
+        for caps in placeholder.captures_iter(line) {
+            found.push((caps[1].to_string(), idx + 1));
+        }
+    }
+    found
+}
+
+/// Expand `<!-- Include: NAME -->` lines and apply `<!-- Extends: NAME -->`, so the
+/// result is a plain template ready for `render`.
+///
+/// An included template replaces the include line (minus its description). A template
+/// that extends another starts from the base and replaces each of the base's sections
+/// with its own section of the same heading; sections the base doesn't have are added
+/// at the end.
+///
+/// A name refers to the first template on the search path that isn't already being
+/// resolved, so `.nira/templates/default.md` can extend the built-in `default`.
+pub fn resolve(template: &Template, root: &Path) -> io::Result<String> {
+    resolve_within(&template.content, root, &mut vec![template.clone()])
+}
+
+fn resolve_within(content: &str, root: &Path, stack: &mut Vec<Template>) -> io::Result<String> {
+    let mut lines: Vec<String> = vec![];
+    let mut base = None;
+    for line in content.lines() {
+        if let Some(name) = header_value(line, "Include") {
+            let fragment = resolve_named(name, root, stack)?;
+            lines.extend(fragment.lines().filter(|l| header_value(l, "Description").is_none()).map(String::from));
+        } else if let Some(name) = header_value(line, "Extends") {
+            base = Some(name.to_string());
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+    let child = join_lines(&lines, content.ends_with('\n'));
+
+    match base {
+        Some(name) => {
+            let base = resolve_named(&name, root, stack)?;
+            Ok(extend(&base, &child))
+        }
+        None => Ok(child),
+    }
+}
+
+/// Resolve template `name`, failing on a missing template or a cycle
+fn resolve_named(name: &str, root: &Path, stack: &mut Vec<Template>) -> io::Result<String> {
+    let same = |a: &Template, b: &Template| a.name == b.name && a.source == b.source && a.path == b.path;
+    let candidates: Vec<Template> = all_templates(root).into_iter().filter(|t| t.name == name).collect();
+    if candidates.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("Template '{}' not found", name)));
+    }
+    let Some(template) = candidates.into_iter().find(|t| !stack.iter().any(|s| same(s, t))) else {
+        let chain: Vec<&str> = stack.iter().map(|t| t.name.as_str()).collect();
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Template cycle: {} -> {}", chain.join(" -> "), name),
+        ));
+    };
+
+    stack.push(template.clone());
+    let resolved = resolve_within(&template.content, root, stack);
+    stack.pop();
+    resolved
+}
+
+/// `child`'s header followed by `base` with its sections overridden by `child`'s.
+/// A `#` title in the child replaces the base's title rather than the whole base.
+fn extend(base: &str, child: &str) -> String {
+    let child_lines: Vec<&str> = child.lines().collect();
+    let child_headings = crate::parser::outline(child);
+
+    // Header: everything before the child's first heading, then the base's own header
+    // without its description
+    let header_end = child_headings.first().map_or(child_lines.len(), |h| h.line - 1);
+    let mut result: Vec<String> = child_lines[..header_end].iter().map(|l| l.to_string()).collect();
+    let mut body: Vec<String> = base
+        .lines()
+        .filter(|l| header_value(l, "Description").is_none())
+        .map(String::from)
+        .collect();
+
+    // Top-level child sections, each with its subsections
+    let mut next_line = 0;
+    for heading in &child_headings {
+        if heading.line <= next_line {
+            continue;
+        }
+        if heading.level == 1 {
+            // A `#` title spans the whole document, so it only replaces the base's title
+            // (and the intro under it, if it has one); its sections merge one by one
+            let intro_end = child_headings.iter().find(|h| h.line > heading.line).map_or(child_lines.len(), |h| h.line - 1);
+            let mut intro: Vec<String> = child_lines[heading.line - 1..intro_end].iter().map(|l| l.to_string()).collect();
+            let base_headings = crate::parser::outline(&join_lines(&body, false));
+            match base_headings.iter().find(|h| h.level == 1) {
+                Some(title) => {
+                    let base_intro_end = base_headings.iter().find(|h| h.line > title.line).map_or(body.len(), |h| h.line - 1);
+                    let end = if intro[1..].iter().all(|l| l.trim().is_empty()) {
+                        intro.truncate(1);
+                        title.line
+                    } else {
+                        base_intro_end
+                    };
+                    body.splice(title.line - 1..end, intro);
+                }
+                None => {
+                    body.splice(0..0, intro);
+                }
+            }
+            continue;
+        }
+        next_line = heading.end_line;
+        let section = &child_lines[heading.line - 1..heading.end_line];
+
+        let current = join_lines(&body, false);
+        let title = heading.title.to_lowercase();
+        match crate::parser::outline(&current).into_iter().find(|h| h.title.to_lowercase() == title) {
+            Some(target) => {
+                body.splice(target.line - 1..target.end_line, section.iter().map(|l| l.to_string()));
+            }
+            None => body.extend(section.iter().map(|l| l.to_string())),
+        }
+    }
+
+    result.append(&mut body);
+    join_lines(&result, child.ends_with('\n') || base.ends_with('\n'))
+}
+
+fn join_lines<S: AsRef<str>>(lines: &[S], trailing_newline: bool) -> String {
+    let mut joined = lines.iter().map(|l| l.as_ref()).collect::<Vec<_>>().join("\n");
+    if trailing_newline {
+        joined.push('\n');
+    }
+    joined
+}
+
+/// Value of a one-line `<!-- Key: value -->` header comment
+fn header_value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let value = line.trim().strip_prefix("<!--")?.strip_suffix("-->")?.trim();
+    let (found, value) = value.split_once(':')?;
+    (found.trim() == key).then(|| value.trim())
+}
+
+/// Sections a blueprint made from the template must have, declared with
+/// `<!-- Schema: NAME -->` (`layers` when absent)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Schema {
+    /// The four layers, with the task lanes in Layer 4
+    Layers,
+    /// Only the task lanes, so `nira task` and `nira prompt` can work with it
+    Tasks,
+    /// No required sections
+    Free,
+}
+
+impl Schema {
+    /// Headings that must exist, matched the same way `parser::find_section` matches them
+    pub fn required_sections(&self) -> &'static [&'static str] {
+        const LANES: [&str; 4] = ["### DONE", "### IN PROGRESS", "### NEXT UP", "### ICEBOX"];
+        match self {
+            Schema::Layers => &[
+                "Layer 1: Intent Map",
+                "Layer 2: Interface Contracts",
+                "Layer 3: File Skeleton",
+                "Layer 4: Task Queue",
+                LANES[0],
+                LANES[1],
+                LANES[2],
+                LANES[3],
+            ],
+            Schema::Tasks => &LANES,
+            Schema::Free => &[],
+        }
+    }
+}
+
+impl std::fmt::Display for Schema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Schema::Layers => write!(f, "layers"),
+            Schema::Tasks => write!(f, "tasks"),
+            Schema::Free => write!(f, "free"),
+        }
+    }
+}
+
+impl std::str::FromStr for Schema {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "layers" => Ok(Schema::Layers),
+            "tasks" => Ok(Schema::Tasks),
+            "free" => Ok(Schema::Free),
+            other => Err(format!("Unknown schema '{}' (expected layers, tasks or free)", other)),
+        }
+    }
+}
+
+/// The schema a template declares
+pub fn schema(content: &str) -> Result<Schema, String> {
+    content
+        .lines()
+        .find_map(|line| header_value(line, "Schema"))
+        .map_or(Ok(Schema::Layers), str::parse)
+}
+
+/// A problem `lint` found in a template
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Check that a template resolves, that its variables and placeholders line up, and that
+/// the blueprint it produces has the sections its schema requires (fragments, `_name`,
+/// are only checked for resolving)
+pub fn lint(template: &Template, root: &Path) -> Vec<Finding> {
+    let error = |message: String| Finding {
+        severity: Severity::Error,
+        message,
+    };
+    let warning = |message: String| Finding {
+        severity: Severity::Warning,
+        message,
+    };
+
+    let resolved = match resolve(template, root) {
+        Ok(resolved) => resolved,
+        Err(e) => return vec![error(e.to_string())],
+    };
+    // Fragments only make sense inside another template
+    if template.name.starts_with('_') {
+        return vec![];
+    }
+    let mut findings = vec![];
+
+    let schema = schema(&resolved).unwrap_or_else(|e| {
+        findings.push(error(e));
+        Schema::Free
+    });
+
+    // Fill every variable with its default (or first choice, or a sample value) so
+    // the output can be checked like a real blueprint
+    let variables = variables(&resolved);
+    let mut values: HashMap<String, String> = HashMap::from([
+        ("PROJECT_NAME".to_string(), "Example".to_string()),
+        ("DATE".to_string(), "2000-01-01".to_string()),
+    ]);
+    for variable in &variables {
+        if let Some(default) = &variable.default {
+            if !variable.choices.is_empty() && !variable.choices.contains(default) {
+                findings.push(error(format!(
+                    "Default '{}' of {} is not one of its choices ({})",
+                    default,
+                    variable.name,
+                    variable.choices.join(", ")
+                )));
+            }
+        }
+        let sample = variable.default.clone().or_else(|| variable.choices.first().cloned());
+        values.insert(variable.name.clone(), sample.unwrap_or_else(|| "example".to_string()));
+    }
+
+    // Line numbers refer to the resolved template, which is the file itself unless it
+    // extends or includes others
+    let blocks = variables_blocks(&resolved);
+    let used: Vec<(String, usize)> = placeholders(&resolved)
+        .into_iter()
+        .filter(|(_, line)| !blocks.iter().any(|(start, end)| (start + 1..=*end).contains(line)))
+        .collect();
+    for (name, line) in &used {
+        if !values.contains_key(name) {
+            findings.push(error(format!(
+                "{{{}}} on line {} is not declared in a Variables block",
+                name, line
+            )));
+        }
+    }
+    for variable in &variables {
+        if !used.iter().any(|(name, _)| *name == variable.name) {
+            findings.push(warning(format!("{} is declared but never used", variable.name)));
+        }
+    }
+
+    if let Ok(blueprint) = render(&resolved, &values) {
+        for section in schema.required_sections() {
+            if crate::parser::find_section(&blueprint, section).is_none() {
+                findings.push(error(format!(
+                    "Missing '{}' section required by the {} schema",
+                    section.trim_start_matches('#').trim(),
+                    schema
+                )));
+            }
+        }
+        if schema == Schema::Layers && crate::parser::parse(&blueprint, PathBuf::new()).project_name.is_none() {
+            findings.push(error("Missing a '# Blueprint: {PROJECT_NAME}' title".to_string()));
+        }
+    }
+
+    findings
+}
+
+/// Turn a filled-in blueprint back into a template: the project name becomes
+/// `{PROJECT_NAME}`, dates above the first `##` section become `{DATE}`, and each
+/// `(KEY, value)` in `vars` becomes `{KEY}`, declared with that value as its default
+pub fn from_blueprint(content: &str, description: &str, vars: &[(String, String)]) -> String {
+    let mut replacements: Vec<(String, String)> = vars.to_vec();
+    if let Some(name) = crate::parser::title_project_name(content) {
+        replacements.push(("PROJECT_NAME".to_string(), name));
+    }
+
+    let mut in_header = true;
+    let date = Regex::new(r"\b\d{4}-\d{2}-\d{2}\b").unwrap();
+    let mut lines = vec![format!("<!-- Description: {} -->", description)];
+    if !vars.is_empty() {
+        lines.push("<!-- Variables:".to_string());
+        for (key, value) in vars {
+            let label = key.to_lowercase().replace('_', " ");
+            let mut chars = label.chars();
+            let label: String = chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default();
+            lines.push(format!("{}: {} | default: {}", key, label, value));
+        }
+        lines.push("-->".to_string());
+    }
+
+    for line in content.lines() {
+        if header_value(line, "Description").is_some() {
+            continue;
+        }
+        if line.starts_with("## ") {
+            in_header = false;
+        }
+
+        let mut line = line.to_string();
+        for (key, value) in &replacements {
+            line = replace_word(&line, value, &format!("{{{}}}", key));
+        }
+        if in_header {
+            line = date.replace_all(&line, "{DATE}").into_owned();
+        }
+        lines.push(line);
+    }
+
+    join_lines(&lines, true)
+}
+
+/// Replace `from` in `text`, but not where it is part of a longer word
+fn replace_word(text: &str, from: &str, to: &str) -> String {
+    let word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let start = if word(from.chars().next()) { r"\b" } else { "" };
+    let end = if word(from.chars().last()) { r"\b" } else { "" };
+    let pattern = Regex::new(&format!("{}{}{}", start, regex::escape(from), end)).unwrap();
+    pattern.replace_all(text, regex::NoExpand(to)).into_owned()
 }
 
 /// Extract description from template file
 /// Looks for: <!-- Description: text here -->
 fn extract_description(content: &str) -> String {
     for line in content.lines().take(10) {
-        let trimmed = line.trim();
-        if trimmed.starts_with("<!-- Description:") {
-            if let Some(desc_start) = trimmed.strip_prefix("<!-- Description:") {
-                if let Some(desc) = desc_start.strip_suffix("-->") {
-                    return desc.trim().to_string();
-                }
-            }
+        if let Some(desc) = header_value(line, "Description") {
+            return desc.to_string();
         }
     }
 
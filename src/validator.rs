@@ -1,115 +1,199 @@
-use crate::blueprint::{Blueprint, ValidationResult, ValidationStatus};
+use crate::blueprint::{Blueprint, Diagnostic, Range, Severity, TaskItem, ValidationResult, ValidationStatus};
+use crate::parser::{heading_line, placeholder_lines, task_line};
+use crate::config::Loaded;
+use crate::template::Schema;
 
-pub fn validate(bp: &Blueprint) -> Vec<ValidationResult> {
+/// Every rule id with its default severity; `[rules]` in nira.toml can change them
+pub const RULES: [(&str, Severity); 8] = [
+    ("missing-layer", Severity::Error),
+    ("empty-layer", Severity::Error),
+    ("intent-placeholder", Severity::Warning),
+    ("no-tasks", Severity::Error),
+    ("no-active-tasks", Severity::Warning),
+    ("task-missing-context", Severity::Warning),
+    ("task-missing-files", Severity::Warning),
+    ("task-missing-approach", Severity::Warning),
+];
+
+/// Severity of a rule after nira.toml, `None` when it's turned off
+fn severity(rule: &str, config: &Loaded) -> Option<Severity> {
+    let default = RULES.iter().find(|(id, _)| *id == rule).map_or(Severity::Error, |(_, s)| *s);
+    config.severity(rule, default)
+}
+
+fn passed(layer: u8, layer_name: &str, message: String) -> ValidationResult {
+    ValidationResult {
+        layer,
+        layer_name: layer_name.to_string(),
+        status: ValidationStatus::Ok,
+        message,
+        fix: None,
+    }
+}
+
+/// The `nira validate` summary: a line for each check that passed plus one for
+/// every diagnostic, grouped by layer
+pub fn validate(bp: &Blueprint, config: &Loaded) -> Vec<ValidationResult> {
     let mut results = Vec::new();
+    let schema = config.schema();
 
-    // Layer 1: Intent Map
-    results.push(if bp.has_intent {
-        ValidationResult {
-            layer: 1,
-            layer_name: "Intent Map".to_string(),
-            status: ValidationStatus::Ok,
-            message: "Has meaningful content".to_string(),
-        }
-    } else {
-        ValidationResult {
-            layer: 1,
-            layer_name: "Intent Map".to_string(),
-            status: ValidationStatus::Missing,
-            message: "Missing or incomplete - fill in PROJECT, ACTORS, CORE FLOWS, HARD PARTS".to_string(),
+    // Layers 1-3 only apply to blueprints with the layered structure
+    if schema == Schema::Layers {
+        let has_content = [bp.has_intent, bp.has_contracts, bp.has_skeleton];
+        let ok_messages = ["Has meaningful content", "Has interface definitions", "Has file structure defined"];
+        for (((layer, heading, _), present), ok) in CONTENT_LAYERS.into_iter().zip(has_content).zip(ok_messages) {
+            if present {
+                results.push(passed(layer, layer_name(heading), ok.to_string()));
+            }
         }
-    });
+    }
 
-    // Layer 2: Interface Contracts
-    results.push(if bp.has_contracts {
-        ValidationResult {
-            layer: 2,
-            layer_name: "Interface Contracts".to_string(),
-            status: ValidationStatus::Ok,
-            message: "Has interface definitions".to_string(),
-        }
-    } else {
+    let active_tasks = bp.tasks.in_progress.len() + bp.tasks.next_up.len();
+    if schema != Schema::Free && active_tasks > 0 {
+        results.push(passed(4, "Task Queue", format!("{} active tasks", active_tasks)));
+    }
+
+    results.extend(diagnostics(bp, config).into_iter().map(|diagnostic| {
+        let layer_name = match diagnostic.rule {
+            "intent-placeholder" => "Intent Quality",
+            "no-tasks" | "no-active-tasks" => "Task Queue",
+            "missing-layer" | "empty-layer" => layer_name(CONTENT_LAYERS[diagnostic.layer as usize - 1].1),
+            _ => "Task Quality",
+        };
+        let status = match diagnostic.severity {
+            Severity::Error => ValidationStatus::Missing,
+            Severity::Warning => ValidationStatus::Warning,
+        };
         ValidationResult {
-            layer: 2,
-            layer_name: "Interface Contracts".to_string(),
-            status: ValidationStatus::Missing,
-            message: "Missing or incomplete - define your data shapes, capabilities, and boundaries".to_string(),
+            layer: diagnostic.layer,
+            layer_name: layer_name.to_string(),
+            status,
+            message: diagnostic.message,
+            fix: diagnostic.fix,
         }
-    });
+    }));
 
-    // Layer 3: File Skeleton
-    results.push(if bp.has_skeleton {
-        ValidationResult {
-            layer: 3,
-            layer_name: "File Skeleton".to_string(),
-            status: ValidationStatus::Ok,
-            message: "Has file structure defined".to_string(),
+    // Stable, so each layer's passed check stays ahead of its findings
+    results.sort_by_key(|result| result.layer);
+    results
+}
+
+/// "Intent Map" for "Layer 1: Intent Map"
+fn layer_name(heading: &str) -> &str {
+    heading.split_once(": ").map_or(heading, |(_, name)| name)
+}
+
+/// Layers 1-3: heading text, whether the parser found content, and what to fill in
+const CONTENT_LAYERS: [(u8, &str, &str); 3] = [
+    (1, "Layer 1: Intent Map", "Fill in PROJECT, ACTORS, CORE FLOWS and HARD PARTS"),
+    (2, "Layer 2: Interface Contracts", "Define your data shapes, capabilities and boundaries"),
+    (3, "Layer 3: File Skeleton", "Map your interfaces to files on disk"),
+];
+
+/// Every failed check, located in the text with a rule id and a suggested fix, at the
+/// severity nira.toml gives it
+pub fn diagnostics(bp: &Blueprint, config: &Loaded) -> Vec<Diagnostic> {
+    let content = &bp.raw;
+    let mut diagnostics = Vec::new();
+
+    let schema = config.schema();
+
+    let has_content = [bp.has_intent, bp.has_contracts, bp.has_skeleton];
+    let layers = if schema == Schema::Layers { CONTENT_LAYERS.len() } else { 0 };
+    for ((layer, heading, hint), present) in CONTENT_LAYERS.into_iter().zip(has_content).take(layers) {
+        if present {
+            continue;
         }
-    } else {
-        ValidationResult {
-            layer: 3,
-            layer_name: "File Skeleton".to_string(),
-            status: ValidationStatus::Missing,
-            message: "Missing or incomplete - map your interfaces to files on disk".to_string(),
+        diagnostics.push(match heading_line(content, heading) {
+            Some(line) => Diagnostic {
+                rule: "empty-layer",
+                layer,
+                severity: Severity::Error,
+                range: Range::line(content, line),
+                message: format!("Layer {} has no real content yet", layer),
+                fix: Some(hint.to_string()),
+            },
+            None => Diagnostic {
+                rule: "missing-layer",
+                layer,
+                severity: Severity::Error,
+                range: Range::line(content, 1),
+                message: format!("No '{}' section", heading),
+                fix: Some(format!("Add a '## {}' section. {}", heading, hint)),
+            },
+        });
+    }
+
+    if schema == Schema::Layers {
+        for (line, text) in placeholder_lines(content, "Layer 1: Intent Map") {
+            diagnostics.push(Diagnostic {
+                rule: "intent-placeholder",
+                layer: 1,
+                severity: Severity::Warning,
+                range: Range::line(content, line),
+                message: format!("Placeholder not filled in: {}", text),
+                fix: Some("Replace it with your own text, or delete the line".to_string()),
+            });
         }
-    });
+    }
 
     // Layer 4: Task Queue
-    let active_tasks = bp.tasks.in_progress.len() + bp.tasks.next_up.len();
-
-    results.push(if active_tasks > 0 {
-        ValidationResult {
+    let tasks = &bp.tasks;
+    let active = tasks.in_progress.len() + tasks.next_up.len();
+    let queue_line = heading_line(content, "Layer 4: Task Queue").unwrap_or(1);
+    let needs_queue = schema != Schema::Free;
+    if needs_queue && active == 0 && (!tasks.done.is_empty() || !tasks.icebox.is_empty()) {
+        let line = heading_line(content, "### NEXT UP").unwrap_or(queue_line);
+        diagnostics.push(Diagnostic {
+            rule: "no-active-tasks",
             layer: 4,
-            layer_name: "Task Queue".to_string(),
-            status: ValidationStatus::Ok,
-            message: format!("{} active tasks", active_tasks),
-        }
-    } else if bp.tasks.done.len() > 0 || bp.tasks.icebox.len() > 0 {
-        ValidationResult {
+            severity: Severity::Warning,
+            range: Range::line(content, line),
+            message: "No active tasks".to_string(),
+            fix: Some("Move something to IN PROGRESS or NEXT UP (nira task move N next-up)".to_string()),
+        });
+    } else if needs_queue && active == 0 {
+        diagnostics.push(Diagnostic {
+            rule: "no-tasks",
             layer: 4,
-            layer_name: "Task Queue".to_string(),
-            status: ValidationStatus::Warning,
-            message: "No active tasks - move something to IN PROGRESS or NEXT UP".to_string(),
-        }
-    } else {
-        ValidationResult {
-            layer: 4,
-            layer_name: "Task Queue".to_string(),
-            status: ValidationStatus::Missing,
-            message: "No tasks defined - add tasks to guide implementation".to_string(),
-        }
-    });
+            severity: Severity::Error,
+            range: Range::line(content, queue_line),
+            message: "No tasks defined".to_string(),
+            fix: Some("Add tasks to guide implementation (nira task add \"...\")".to_string()),
+        });
+    }
 
-    // Extra warnings for task quality
-    for task in &bp.tasks.in_progress {
+    // Task quality
+    for task in &tasks.in_progress {
         if task.context.is_none() {
-            results.push(ValidationResult {
-                layer: 4,
-                layer_name: "Task Quality".to_string(),
-                status: ValidationStatus::Warning,
-                message: format!("IN PROGRESS task '{}' missing Context", task.text),
-            });
+            diagnostics.push(task_field_missing(content, task, "task-missing-context", "Context"));
         }
         if task.files.is_none() {
-            results.push(ValidationResult {
-                layer: 4,
-                layer_name: "Task Quality".to_string(),
-                status: ValidationStatus::Warning,
-                message: format!("IN PROGRESS task '{}' missing Files", task.text),
-            });
+            diagnostics.push(task_field_missing(content, task, "task-missing-files", "Files"));
         }
     }
-
-    for task in &bp.tasks.next_up {
+    for task in &tasks.next_up {
         if task.approach.is_none() {
-            results.push(ValidationResult {
-                layer: 4,
-                layer_name: "Task Quality".to_string(),
-                status: ValidationStatus::Warning,
-                message: format!("NEXT UP task '{}' missing Approach", task.text),
-            });
+            diagnostics.push(task_field_missing(content, task, "task-missing-approach", "Approach"));
         }
     }
 
-    results
+    diagnostics
+        .into_iter()
+        .filter_map(|mut diagnostic| {
+            diagnostic.severity = severity(diagnostic.rule, config)?;
+            Some(diagnostic)
+        })
+        .collect()
+}
+
+fn task_field_missing(content: &str, task: &TaskItem, rule: &'static str, field: &str) -> Diagnostic {
+    Diagnostic {
+        rule,
+        layer: 4,
+        severity: Severity::Warning,
+        range: Range::line(content, task_line(content, task).unwrap_or(1)),
+        message: format!("Task '{}' is missing {}", task.text, field),
+        fix: Some(format!("Add '  - **{}:** ...' under the task", field)),
+    }
 }
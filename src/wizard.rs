@@ -0,0 +1,189 @@
+use crate::parser::{find_section, placeholder_lines};
+use std::io::{self, BufRead, Write};
+
+/// Answers collected by `nira init --interactive`
+#[derive(Debug, Clone, Default)]
+pub struct Intent {
+    pub one_line: String,
+    pub actors: Vec<String>,
+    pub flows: Vec<String>,
+    pub hard_parts: Vec<String>,
+    pub non_goals: Vec<String>,
+    pub tasks: Vec<String>,
+}
+
+/// Ask for the intent map and first tasks on the terminal. Lists end at an empty
+/// line, so answers can also be piped in.
+pub fn ask() -> io::Result<Intent> {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+
+    println!("Describe the project. For lists, enter one item per line and an empty line to finish.\n");
+    Ok(Intent {
+        one_line: ask_line(&mut input, "What does it do, in one sentence?")?,
+        actors: ask_list(&mut input, "Who or what uses it, and what does it talk to?", true)?,
+        flows: ask_list(&mut input, "Core flows (e.g. User uploads a file → it is scanned → the result is shown)", true)?,
+        hard_parts: ask_list(&mut input, "Hard parts: constraints, edge cases, tricky decisions", true)?,
+        non_goals: ask_list(&mut input, "Non-goals: things it deliberately does NOT do (optional)", false)?,
+        tasks: ask_list(&mut input, "First tasks (optional)", false)?,
+    })
+}
+
+fn read_answer(input: &mut impl BufRead, question: &str) -> io::Result<Option<String>> {
+    let mut answer = String::new();
+    if input.read_line(&mut answer)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("No answer for '{}'", question),
+        ));
+    }
+    let answer = answer.trim();
+    Ok((!answer.is_empty()).then(|| answer.to_string()))
+}
+
+fn ask_line(input: &mut impl BufRead, question: &str) -> io::Result<String> {
+    loop {
+        print!("{} ", question);
+        io::stdout().flush()?;
+        if let Some(answer) = read_answer(input, question)? {
+            println!();
+            return Ok(answer);
+        }
+    }
+}
+
+fn ask_list(input: &mut impl BufRead, question: &str, required: bool) -> io::Result<Vec<String>> {
+    println!("{}", question);
+    let mut items = vec![];
+    loop {
+        print!("  - ");
+        io::stdout().flush()?;
+        // Running out of input ends an optional list like an empty line does
+        let answer = match read_answer(input, question) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof && (!required || !items.is_empty()) => None,
+            other => other?,
+        };
+        match answer {
+            Some(item) => items.push(item),
+            None if required && items.is_empty() => println!("  Enter at least one."),
+            None => break,
+        }
+    }
+    println!();
+    Ok(items)
+}
+
+/// Write the answers into the Layer 1 fields of a rendered template, replacing its
+/// placeholder items, and put the first tasks in NEXT UP in place of the example tasks
+pub fn apply(content: &str, intent: &Intent) -> io::Result<String> {
+    if find_section(content, "Layer 1: Intent Map").is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Template has no 'Layer 1: Intent Map' section to fill in",
+        ));
+    }
+
+    let numbered: Vec<String> = intent.flows.iter().enumerate().map(|(i, f)| format!("{}. {}", i + 1, f)).collect();
+    let bullets = |items: &[String]| items.iter().map(|i| format!("- {}", i)).collect::<Vec<_>>();
+
+    let mut content = fill_line(content, "ONE-LINE", &intent.one_line);
+    content = fill_list(&content, "ACTORS", &bullets(&intent.actors));
+    content = fill_list(&content, "CORE FLOWS", &numbered);
+    content = fill_list(&content, "HARD PARTS", &bullets(&intent.hard_parts));
+    content = fill_list(&content, "NON-GOALS", &bullets(&intent.non_goals));
+
+    if !intent.tasks.is_empty() {
+        content = remove_example_tasks(&content);
+        for task in &intent.tasks {
+            content = crate::tasks::insert_task(&content, task)?;
+        }
+    }
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    Ok(content)
+}
+
+/// Lines of the intent map section, as indices into `lines`
+fn intent_range(lines: &[String]) -> std::ops::Range<usize> {
+    let content = lines.join("\n");
+    match find_section(&content, "Layer 1: Intent Map") {
+        Some((start, end)) => {
+            let first = content[..start].matches('\n').count();
+            let last = content[..end].matches('\n').count();
+            first..last.min(lines.len())
+        }
+        None => 0..0,
+    }
+}
+
+fn fill_line(content: &str, field: &str, text: &str) -> String {
+    let label = format!("**{}:**", field);
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let range = intent_range(&lines);
+    if let Some(line) = lines[range].iter_mut().find(|l| l.trim_start().starts_with(&label)) {
+        *line = format!("{} {}", label, text);
+    }
+    lines.join("\n")
+}
+
+/// Replace the list items under a `**FIELD:**` line, keeping its `>` guidance
+fn fill_list(content: &str, field: &str, items: &[String]) -> String {
+    let label = format!("**{}:**", field);
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let range = intent_range(&lines);
+    let Some(at) = range.clone().find(|&i| lines[i].trim_start().starts_with(&label)) else {
+        return content.to_string();
+    };
+
+    let mut start = at + 1;
+    while start < range.end && lines[start].trim_start().starts_with('>') {
+        start += 1;
+    }
+    let mut end = start;
+    while end < range.end && is_list_item(&lines[end]) {
+        end += 1;
+    }
+    lines.splice(start..end, items.iter().cloned());
+    lines.join("\n")
+}
+
+fn is_list_item(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("- ") || line.split_once(". ").is_some_and(|(n, _)| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Drop the `[task description]` examples from every lane, with their sub-items
+fn remove_example_tasks(content: &str) -> String {
+    let mut placeholders = vec![];
+    for lane in ["### DONE", "### IN PROGRESS", "### NEXT UP", "### ICEBOX"] {
+        placeholders.extend(
+            placeholder_lines(content, lane)
+                .into_iter()
+                .filter(|(_, line)| line.starts_with("- [")),
+        );
+    }
+
+    let lines: Vec<&str> = content.lines().collect();
+    let mut drop = vec![false; lines.len()];
+    for (line, _) in placeholders {
+        let mut idx = line - 1;
+        drop[idx] = true;
+        idx += 1;
+        while idx < lines.len() && lines[idx].starts_with(char::is_whitespace) && !lines[idx].trim().is_empty() {
+            drop[idx] = true;
+            idx += 1;
+        }
+        // Don't leave two blank lines where the task was
+        if idx < lines.len() && lines[idx].trim().is_empty() && line >= 2 && lines[line - 2].trim().is_empty() {
+            drop[idx] = true;
+        }
+    }
+    lines
+        .iter()
+        .zip(drop)
+        .filter(|(_, dropped)| !dropped)
+        .map(|(line, _)| *line)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,79 @@
+use crate::config::Loaded;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Directories never searched for blueprints
+const SKIP_DIRS: [&str; 3] = ["target", "node_modules", "templates"];
+
+/// Every blueprint under `root` that `config` doesn't ignore, top-level ones first, then by path
+pub fn discover(root: &Path, config: &Loaded) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    walk(root, config, &mut found)?;
+
+    found.sort_by(|a, b| {
+        let depth = |p: &Path| p.components().count();
+        depth(a).cmp(&depth(b)).then_with(|| a.cmp(b))
+    });
+    Ok(found)
+}
+
+fn walk(dir: &Path, config: &Loaded, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)?.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let Ok(file_type) = entry.file_type() else { continue };
+
+        if config.is_ignored(&path) {
+            continue;
+        }
+        if file_type.is_dir() {
+            if !name.starts_with('.') && !SKIP_DIRS.contains(&name.as_ref()) {
+                walk(&path, config, found)?;
+            }
+        } else if name.ends_with(".md") {
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                if is_blueprint(&content) {
+                    found.push(path);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A filled-in blueprint: has a `# Blueprint:` title or a Task Queue layer,
+/// and is not an unrendered template
+pub fn is_blueprint(content: &str) -> bool {
+    if content.contains("{PROJECT_NAME}") {
+        return false;
+    }
+    content.lines().any(|line| {
+        let line = line.trim();
+        line.to_lowercase().starts_with("# blueprint:")
+            || (line.starts_with('#') && line.contains("Layer 4: Task Queue"))
+    })
+}
+
+/// URL-safe id for a blueprint, from its path relative to the workspace root
+/// (`crates/parser/niraprint.md` becomes `crates-parser-niraprint`)
+pub fn doc_id(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path).with_extension("");
+    let mut id = String::new();
+    for c in relative.to_string_lossy().chars() {
+        if c.is_ascii_alphanumeric() {
+            id.push(c.to_ascii_lowercase());
+        } else if !id.ends_with('-') {
+            id.push('-');
+        }
+    }
+    let id = id.trim_matches('-').to_string();
+    if id.is_empty() {
+        "blueprint".to_string()
+    } else {
+        id
+    }
+}